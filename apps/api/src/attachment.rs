@@ -0,0 +1,277 @@
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+use tokio::sync::RwLock;
+
+use crate::{config::AttachmentConfig, db, store::LinkStore, token};
+
+// How long a commence grant may wait before its download.
+pub const GRANT_TTL: Duration = Duration::from_secs(10 * 60);
+const MAX_FILENAME_LEN: usize = 128;
+
+#[derive(Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Store {
+    Sqlite,
+    Disk,
+}
+
+// What a file really is, from its first bytes. The client's Content-Type
+// is not asked: anything outside this list is refused.
+pub fn sniff(bytes: &[u8]) -> Option<&'static str> {
+    let at = |offset: usize, magic: &[u8]| bytes.get(offset..offset + magic.len()) == Some(magic);
+    if at(0, b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if at(0, b"\xff\xd8\xff") {
+        Some("image/jpeg")
+    } else if at(0, b"GIF87a") || at(0, b"GIF89a") {
+        Some("image/gif")
+    } else if at(0, b"RIFF") && at(8, b"WEBP") {
+        Some("image/webp")
+    } else if at(0, b"%PDF-") {
+        Some("application/pdf")
+    } else if at(0, b"ID3") || (bytes.len() > 1 && bytes[0] == 0xff && bytes[1] & 0xe0 == 0xe0) {
+        Some("audio/mpeg")
+    } else if at(0, b"OggS") {
+        Some("audio/ogg")
+    } else if at(0, b"RIFF") && at(8, b"WAVE") {
+        Some("audio/wav")
+    } else if at(0, b"fLaC") {
+        Some("audio/flac")
+    } else if at(4, b"ftypM4A") {
+        Some("audio/mp4")
+    } else if at(0, b"\x1a\x45\xdf\xa3") {
+        Some("audio/webm")
+    } else {
+        None
+    }
+}
+
+// Keeps a display name safe for Content-Disposition: no paths, no quotes,
+// no control characters.
+pub fn clean_filename(raw: &str) -> Option<String> {
+    let base = raw.rsplit(['/', '\\']).next().unwrap_or(raw);
+    let cleaned: String = base
+        .chars()
+        .filter(|c| !c.is_control() && !matches!(c, '"' | ';'))
+        .take(MAX_FILENAME_LEN)
+        .collect();
+    let cleaned = cleaned.trim().trim_start_matches('.').to_string();
+    (!cleaned.is_empty()).then_some(cleaned)
+}
+
+pub struct Upload {
+    pub content_type: &'static str,
+    pub filename: Option<String>,
+    pub bytes: Vec<u8>,
+}
+
+pub struct Download {
+    pub content_type: String,
+    pub filename: Option<String>,
+    pub bytes: Vec<u8>,
+}
+
+struct Grant {
+    id: i64,
+    path: String,
+    expires: Instant,
+}
+
+// One file per link. Its bytes leave the server only on a grant handed out
+// by a successful commence, one download per grant.
+pub struct Attachments {
+    cfg: AttachmentConfig,
+    grants: RwLock<HashMap<String, Grant>>,
+}
+
+impl Attachments {
+    pub fn new(cfg: AttachmentConfig) -> anyhow::Result<Self> {
+        if cfg.store == Store::Disk && cfg.max_bytes > 0 {
+            std::fs::create_dir_all(&cfg.dir)
+                .map_err(|err| anyhow::anyhow!("attachments.dir {}: {err}", cfg.dir.display()))?;
+        }
+        Ok(Self {
+            cfg,
+            grants: RwLock::new(HashMap::new()),
+        })
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.cfg.max_bytes > 0
+    }
+
+    pub fn max_bytes(&self) -> usize {
+        self.cfg.max_bytes
+    }
+
+    pub async fn save(
+        &self,
+        pool: &SqlitePool,
+        ns: &str,
+        slug: &str,
+        upload: &Upload,
+        now: i64,
+    ) -> anyhow::Result<()> {
+        // A released file of an earlier link with this path goes first.
+        self.remove(pool, ns, slug).await;
+        let file = match self.cfg.store {
+            Store::Sqlite => None,
+            Store::Disk => {
+                let name = format!("{}.bin", token::gen_token());
+                tokio::fs::write(self.cfg.dir.join(&name), &upload.bytes).await?;
+                Some(name)
+            }
+        };
+        let row = db::NewAttachment {
+            callsign: ns,
+            slug,
+            content_type: upload.content_type,
+            filename: upload.filename.as_deref(),
+            size: upload.bytes.len() as i64,
+            data: file.is_none().then_some(upload.bytes.as_slice()),
+            file: file.as_deref(),
+            created_at: now,
+        };
+        if let Err(err) = db::insert_attachment(pool, &row).await {
+            self.delete_file(file).await;
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    // Link burned or expired: the file goes with it, grants or not.
+    pub async fn remove(&self, pool: &SqlitePool, ns: &str, slug: &str) {
+        match db::delete_attachment(pool, ns, slug).await {
+            Ok(Some(file)) => self.delete_file(file).await,
+            Ok(None) => {}
+            Err(err) => tracing::warn!(error = ?err, "attachment delete failed"),
+        }
+    }
+
+    // Some(token) when the link has a file. Called right after a commence;
+    // if that was the last view, release() already ran and the file now
+    // waits only for this download.
+    pub async fn grant(
+        &self,
+        pool: &SqlitePool,
+        ns: &str,
+        slug: &str,
+        path: &str,
+    ) -> anyhow::Result<Option<(String, db::AttachmentMeta)>> {
+        let Some(meta) = db::attachment_meta(pool, ns, slug).await? else {
+            return Ok(None);
+        };
+        let grant_token = token::gen_token();
+        self.grants.write().await.insert(
+            token::hash_token(&grant_token),
+            Grant {
+                id: meta.id,
+                path: path.to_string(),
+                expires: Instant::now() + GRANT_TTL,
+            },
+        );
+        Ok(Some((grant_token, meta)))
+    }
+
+    // Spends the grant. None = bad / used / expired token, or the file is gone.
+    pub async fn take(
+        &self,
+        pool: &SqlitePool,
+        path: &str,
+        grant_token: &str,
+    ) -> anyhow::Result<Option<Download>> {
+        let hash = token::hash_token(grant_token);
+        let id = {
+            let mut grants = self.grants.write().await;
+            match grants.get(&hash) {
+                Some(grant) if grant.path == path && grant.expires > Instant::now() => {
+                    let id = grant.id;
+                    grants.remove(&hash);
+                    id
+                }
+                _ => return Ok(None),
+            }
+        };
+        let Some(stored) = db::load_attachment(pool, id).await? else {
+            return Ok(None);
+        };
+        let bytes = match (stored.data, stored.file.as_deref()) {
+            (Some(data), _) => data,
+            (None, Some(file)) => tokio::fs::read(self.cfg.dir.join(file)).await?,
+            (None, None) => return Ok(None),
+        };
+        // Last view already spent: this download was the file's last.
+        if stored.released {
+            if let Some(file) = db::delete_attachment_id(pool, id).await? {
+                self.delete_file(file).await;
+            }
+        }
+        Ok(Some(Download {
+            content_type: stored.content_type,
+            filename: stored.filename,
+            bytes,
+        }))
+    }
+
+    // Last view spent: the file now waits only for the download that
+    // commence granted.
+    pub async fn release(&self, pool: &SqlitePool, ns: &str, slug: &str, now: i64) {
+        if let Err(err) = db::release_attachment(pool, ns, slug, now).await {
+            tracing::warn!(error = ?err, "attachment release failed");
+        }
+    }
+
+    // Released files whose grant ran out, and files whose link vanished
+    // without telling us (crash between the two deletes). The links may
+    // live in another database, so the orphan check asks the store.
+    pub async fn sweep(&self, pool: &SqlitePool, store: &dyn LinkStore, now: i64) {
+        let released_before = now - GRANT_TTL.as_secs() as i64;
+        match db::purge_attachments(pool, released_before).await {
+            Ok(files) => {
+                for file in files {
+                    self.delete_file(file).await;
+                }
+            }
+            Err(err) => tracing::warn!(error = ?err, "attachment sweep failed"),
+        }
+        if let Err(err) = self.purge_orphans(pool, store, released_before).await {
+            tracing::warn!(error = ?err, "attachment orphan sweep failed");
+        }
+        let now = Instant::now();
+        self.grants
+            .write()
+            .await
+            .retain(|_, grant| grant.expires > now);
+    }
+
+    async fn purge_orphans(
+        &self,
+        pool: &SqlitePool,
+        store: &dyn LinkStore,
+        created_before: i64,
+    ) -> anyhow::Result<()> {
+        for (id, ns, slug) in db::held_attachments(pool, created_before).await? {
+            if store.owner_hash(&ns, &slug).await?.is_none() {
+                if let Some(file) = db::delete_attachment_id(pool, id).await? {
+                    self.delete_file(file).await;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn delete_file(&self, file: Option<String>) {
+        let Some(name) = file else {
+            return;
+        };
+        if let Err(err) = tokio::fs::remove_file(self.cfg.dir.join(&name)).await {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                tracing::warn!(error = ?err, file = %name, "attachment file delete failed");
+            }
+        }
+    }
+}
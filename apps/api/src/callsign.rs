@@ -0,0 +1,99 @@
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Path},
+    http::{request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+};
+use std::collections::HashMap;
+
+// The public commons. Links without a callsign live here (stored as '').
+pub const DEFAULT: &str = "garden";
+
+const MIN_LEN: usize = 2;
+const MAX_LEN: usize = 24;
+const RESERVED: &[&str] = &[DEFAULT, "amigo", "admin", "api", "root", "system"];
+
+// "@Crew" / "crew" -> "crew"; the default callsign maps to the '' namespace.
+pub fn namespace(input: &str) -> Option<String> {
+    let name = input.trim().trim_start_matches('@').to_ascii_lowercase();
+    if name == DEFAULT {
+        return Some(String::new());
+    }
+    if name.len() < MIN_LEN
+        || name.len() > MAX_LEN
+        || !name
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+    {
+        return None;
+    }
+    Some(name)
+}
+
+pub fn check_claim(input: &str) -> Result<String, &'static str> {
+    let name = input.trim().trim_start_matches('@').to_ascii_lowercase();
+    if RESERVED.contains(&name.as_str()) {
+        return Err("callsign is reserved\n");
+    }
+    match namespace(&name) {
+        Some(name) if !name.starts_with('-') && !name.ends_with('-') => Ok(name),
+        _ => Err("callsign must be 2-24 characters of a-z, 0-9 and '-'\n"),
+    }
+}
+
+// Link address from the path: `/:slug` or `/:callsign/:slug` (callsign
+// segment must carry the '@').
+pub struct LinkKey {
+    pub ns: String,
+    pub slug: String,
+}
+
+impl LinkKey {
+    // Inverse of `path`: "slug" or "@crew/slug". Slugs are a-z, 0-9, '-'.
+    pub fn parse(path: &str) -> Option<Self> {
+        let path = path.trim().trim_matches('/');
+        let (ns, slug) = match path.split_once('/') {
+            Some((cs, slug)) if cs.starts_with('@') => (namespace(cs)?, slug),
+            Some(_) => return None,
+            None => (String::new(), path),
+        };
+        let valid = !slug.is_empty()
+            && slug.len() <= 32
+            && slug
+                .bytes()
+                .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-');
+        valid.then(|| Self {
+            ns,
+            slug: slug.to_string(),
+        })
+    }
+
+    // Path form: "slug" in the commons, "@crew/slug" otherwise.
+    pub fn path(&self) -> String {
+        if self.ns.is_empty() {
+            self.slug.clone()
+        } else {
+            format!("@{}/{}", self.ns, self.slug)
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for LinkKey {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Path(params) = Path::<HashMap<String, String>>::from_request_parts(parts, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        let not_found = || (StatusCode::NOT_FOUND, "not-found\n").into_response();
+
+        let slug = params.get("slug").cloned().ok_or_else(not_found)?;
+        let ns = match params.get("callsign") {
+            Some(raw) if raw.starts_with('@') => namespace(raw).ok_or_else(not_found)?,
+            Some(_) => return Err(not_found()),
+            None => String::new(),
+        };
+        Ok(Self { ns, slug })
+    }
+}
@@ -0,0 +1,735 @@
+use serde::{Deserialize, Deserializer};
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
+
+use crate::{attachment::Store, ratelimit, registry::SignetMode, ttl};
+
+// Read when neither --config nor AMIGO_CONFIG names a file; missing is fine.
+const DEFAULT_PATH: &str = "amigo.toml";
+
+// Every knob in one place: amigo.toml (see amigo.example.toml), then the
+// env vars on top, then validate. A bad value stops the boot with the
+// key that holds it.
+#[derive(Deserialize, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub links: LinksConfig,
+    pub sweep: SweepConfig,
+    pub joint: JointConfig,
+    pub rate_limit: RateLimitConfig,
+    pub webhooks: WebhookConfig,
+    pub denylist: DenylistConfig,
+    pub tracking: TrackingConfig,
+    pub signets: SignetConfig,
+    pub attachments: AttachmentConfig,
+    pub metrics: MetricsConfig,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub port: u16,
+    pub base_url: String,
+    pub web_base_url: String,
+    // Empty = any origin.
+    pub cors_allow_origins: Vec<String>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            port: 3000,
+            base_url: "http://localhost:3000".to_string(),
+            web_base_url: "http://localhost:3001".to_string(),
+            cors_allow_origins: vec![
+                "https://amigo.sh".to_string(),
+                "http://localhost:3001".to_string(),
+                "http://localhost:3000".to_string(),
+            ],
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub url: String,
+    pub max_connections: u32,
+    // Zero freed pages, so burned / purged notes don't linger in the file.
+    pub secure_delete: bool,
+    // Where links live: unset = this SQLite file, "memory", or a
+    // postgres:// url shared by several instances. Only the links are
+    // shared: unlock tokens, commence grants, receipt streams and rate
+    // buckets stay in the instance that made them, so postgres needs
+    // sticky routing, and attachments (local files) must be off.
+    pub link_store: Option<String>,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            url: "sqlite:./data/amigo.db".to_string(),
+            max_connections: 5,
+            secure_delete: false,
+            link_store: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct LinksConfig {
+    // Seconds; TOML takes "7d" or a number.
+    #[serde(deserialize_with = "de_duration")]
+    pub default_ttl: i64,
+    #[serde(deserialize_with = "de_duration")]
+    pub max_ttl: i64,
+    // Random slugs; a collision retries one character longer.
+    pub slug_length: usize,
+    pub allow_private_urls: bool,
+}
+
+impl Default for LinksConfig {
+    fn default() -> Self {
+        Self {
+            default_ttl: 7 * 24 * 3600,
+            max_ttl: 365 * 24 * 3600,
+            slug_length: 6,
+            allow_private_urls: false,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct SweepConfig {
+    pub interval_secs: u64,
+    pub batch_size: i64,
+    pub vacuum: bool,
+    // In-memory housekeeping: idle joint rooms, unlock tokens, receipts,
+    // rate buckets, released attachments.
+    pub gc_interval_secs: u64,
+}
+
+impl Default for SweepConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 300,
+            batch_size: 500,
+            vacuum: false,
+            gc_interval_secs: 30,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct JointConfig {
+    // A room nobody spoke in for this long burns.
+    pub idle_secs: u64,
+    // Messages a slow socket may fall behind before it skips some.
+    pub broadcast_buffer: usize,
+}
+
+impl Default for JointConfig {
+    fn default() -> Self {
+        Self {
+            idle_secs: 300,
+            broadcast_buffer: 64,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    // "30/1m"; "off" = no limit.
+    #[serde(deserialize_with = "de_rate")]
+    pub dispatch: Option<ratelimit::Rule>,
+    #[serde(deserialize_with = "de_rate")]
+    pub joint: Option<ratelimit::Rule>,
+    // Proxies in front of us that append to X-Forwarded-For (Railway: 1).
+    pub trusted_proxy_hops: usize,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            dispatch: ratelimit::Rule::parse("30/1m").ok().flatten(),
+            joint: ratelimit::Rule::parse("10/1m").ok().flatten(),
+            trusted_proxy_hops: 0,
+        }
+    }
+}
+
+// Server-wide hooks (every link, every event), signed with the secret.
+#[derive(Deserialize, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebhookConfig {
+    pub urls: Vec<String>,
+    pub secret: Option<String>,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct DenylistConfig {
+    pub path: Option<PathBuf>,
+    pub reload_secs: u64,
+}
+
+impl Default for DenylistConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            reload_secs: 10,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrackingConfig {
+    pub strip: bool,
+    pub rules_path: Option<PathBuf>,
+}
+
+impl Default for TrackingConfig {
+    fn default() -> Self {
+        Self {
+            strip: true,
+            rules_path: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct SignetConfig {
+    pub mode: SignetMode,
+    // Replaces the shipped signets.toml.
+    pub rules_path: Option<PathBuf>,
+}
+
+impl Default for SignetConfig {
+    fn default() -> Self {
+        Self {
+            mode: SignetMode::Free,
+            rules_path: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct AttachmentConfig {
+    // 0 = uploads off; TOML also takes "off".
+    #[serde(deserialize_with = "de_bytes")]
+    pub max_bytes: usize,
+    pub store: Store,
+    pub dir: PathBuf,
+}
+
+impl Default for AttachmentConfig {
+    fn default() -> Self {
+        Self {
+            max_bytes: 5 * 1024 * 1024,
+            store: Store::Sqlite,
+            dir: PathBuf::from("data/attachments"),
+        }
+    }
+}
+
+// Only the addr we listen on; unset = /metrics is not served at all.
+#[derive(Deserialize, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsConfig {
+    pub addr: Option<SocketAddr>,
+}
+
+impl Config {
+    pub fn load(path: Option<&Path>) -> anyhow::Result<Self> {
+        let mut cfg = match path {
+            Some(path) => Self::read(path)?,
+            None if Path::new(DEFAULT_PATH).exists() => Self::read(Path::new(DEFAULT_PATH))?,
+            None => Self::default(),
+        };
+        cfg.apply_env()?;
+        cfg.validate()?;
+        Ok(cfg)
+    }
+
+    fn read(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| anyhow::anyhow!("config {}: {err}", path.display()))?;
+        let cfg = toml::from_str(&text)
+            .map_err(|err| anyhow::anyhow!("config {}: {err}", path.display()))?;
+        tracing::info!(path = %path.display(), "config loaded");
+        Ok(cfg)
+    }
+
+    // The env names predate the file and keep working; they win over it.
+    fn apply_env(&mut self) -> anyhow::Result<()> {
+        env("PORT", &mut self.server.port, number)?;
+        env("BASE_URL", &mut self.server.base_url, text)?;
+        env("WEB_BASE_URL", &mut self.server.web_base_url, text)?;
+        env(
+            "CORS_ALLOW_ORIGINS",
+            &mut self.server.cors_allow_origins,
+            list,
+        )?;
+
+        env("DATABASE_URL", &mut self.database.url, text)?;
+        env(
+            "DATABASE_MAX_CONNECTIONS",
+            &mut self.database.max_connections,
+            number,
+        )?;
+        env(
+            "SQLITE_SECURE_DELETE",
+            &mut self.database.secure_delete,
+            flag,
+        )?;
+        env("LINK_STORE_URL", &mut self.database.link_store, optional)?;
+
+        env(
+            "DEFAULT_TTL",
+            &mut self.links.default_ttl,
+            ttl::parse_duration,
+        )?;
+        env("MAX_TTL", &mut self.links.max_ttl, ttl::parse_duration)?;
+        env("SLUG_LENGTH", &mut self.links.slug_length, number)?;
+        env(
+            "ALLOW_PRIVATE_URLS",
+            &mut self.links.allow_private_urls,
+            flag,
+        )?;
+
+        env("SWEEP_INTERVAL_SECS", &mut self.sweep.interval_secs, number)?;
+        env("SWEEP_BATCH_SIZE", &mut self.sweep.batch_size, number)?;
+        env("SWEEP_VACUUM", &mut self.sweep.vacuum, flag)?;
+        env(
+            "SWEEP_GC_INTERVAL_SECS",
+            &mut self.sweep.gc_interval_secs,
+            number,
+        )?;
+
+        env("JOINT_IDLE_SECS", &mut self.joint.idle_secs, number)?;
+        env(
+            "JOINT_BROADCAST_BUFFER",
+            &mut self.joint.broadcast_buffer,
+            number,
+        )?;
+
+        env(
+            "RATE_LIMIT_DISPATCH",
+            &mut self.rate_limit.dispatch,
+            ratelimit::Rule::parse,
+        )?;
+        env(
+            "RATE_LIMIT_JOINT",
+            &mut self.rate_limit.joint,
+            ratelimit::Rule::parse,
+        )?;
+        env(
+            "TRUSTED_PROXY_HOPS",
+            &mut self.rate_limit.trusted_proxy_hops,
+            number,
+        )?;
+
+        env("WEBHOOK_URLS", &mut self.webhooks.urls, list)?;
+        env("WEBHOOK_SECRET", &mut self.webhooks.secret, optional)?;
+
+        env("DENYLIST_PATH", &mut self.denylist.path, path)?;
+        env(
+            "DENYLIST_RELOAD_SECS",
+            &mut self.denylist.reload_secs,
+            number,
+        )?;
+
+        env("TRACKING_STRIP", &mut self.tracking.strip, flag)?;
+        env("TRACKING_RULES_PATH", &mut self.tracking.rules_path, path)?;
+
+        env("SIGNET_MODE", &mut self.signets.mode, signet_mode)?;
+        env("SIGNET_RULES_PATH", &mut self.signets.rules_path, path)?;
+
+        env(
+            "ATTACHMENT_MAX_BYTES",
+            &mut self.attachments.max_bytes,
+            bytes,
+        )?;
+        env("ATTACHMENT_STORE", &mut self.attachments.store, store)?;
+        env("ATTACHMENT_DIR", &mut self.attachments.dir, |raw| {
+            Ok(PathBuf::from(raw))
+        })?;
+
+        env("METRICS_ADDR", &mut self.metrics.addr, |raw| match raw {
+            "off" => Ok(None),
+            addr => addr
+                .parse()
+                .map(Some)
+                .map_err(|_| format!("expected host:port, got {addr}")),
+        })?;
+        Ok(())
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        let check = |ok: bool, key: &str, msg: &str| {
+            if ok {
+                Ok(())
+            } else {
+                Err(anyhow::anyhow!("config: {key}: {msg}"))
+            }
+        };
+        let http = |url: &str| url.starts_with("http://") || url.starts_with("https://");
+
+        check(self.server.port > 0, "server.port", "must be > 0")?;
+        check(
+            http(&self.server.base_url),
+            "server.base_url",
+            "not an http(s) url",
+        )?;
+        check(
+            http(&self.server.web_base_url),
+            "server.web_base_url",
+            "not an http(s) url",
+        )?;
+        check(
+            self.database.url.starts_with("sqlite:"),
+            "database.url",
+            "expected sqlite:<path>",
+        )?;
+        check(
+            self.database.link_store.as_deref().is_none_or(|url| {
+                url == "memory"
+                    || url.starts_with("postgres://")
+                    || url.starts_with("postgresql://")
+            }),
+            "database.link_store",
+            "expected memory or postgres://...",
+        )?;
+        check(
+            self.database.max_connections > 0,
+            "database.max_connections",
+            "must be > 0",
+        )?;
+        check(
+            self.links.default_ttl > 0 && self.links.default_ttl <= self.links.max_ttl,
+            "links.default_ttl",
+            "must be > 0 and <= links.max_ttl",
+        )?;
+        check(
+            (4..=16).contains(&self.links.slug_length),
+            "links.slug_length",
+            "must be 4-16",
+        )?;
+        check(
+            self.sweep.interval_secs > 0,
+            "sweep.interval_secs",
+            "must be > 0",
+        )?;
+        check(self.sweep.batch_size > 0, "sweep.batch_size", "must be > 0")?;
+        check(
+            self.sweep.gc_interval_secs > 0,
+            "sweep.gc_interval_secs",
+            "must be > 0",
+        )?;
+        check(self.joint.idle_secs > 0, "joint.idle_secs", "must be > 0")?;
+        check(
+            self.joint.broadcast_buffer > 0,
+            "joint.broadcast_buffer",
+            "must be > 0",
+        )?;
+        check(
+            self.webhooks.urls.is_empty() || self.webhooks.secret.is_some(),
+            "webhooks.urls",
+            "needs webhooks.secret to sign deliveries",
+        )?;
+        if let Some(url) = self.webhooks.urls.iter().find(|url| !http(url)) {
+            anyhow::bail!("config: webhooks.urls: not an http(s) url: {url}");
+        }
+        check(
+            self.denylist.reload_secs > 0,
+            "denylist.reload_secs",
+            "must be > 0",
+        )?;
+        check(
+            !self.attachments.dir.as_os_str().is_empty(),
+            "attachments.dir",
+            "must not be empty",
+        )?;
+        check(
+            self.attachments.max_bytes == 0 || !self.shared_links(),
+            "attachments.max_bytes",
+            "attachments stay on one instance; set 0 (ATTACHMENT_MAX_BYTES=off) with a postgres link_store",
+        )?;
+        Ok(())
+    }
+
+    // Links in a database other instances read too.
+    fn shared_links(&self) -> bool {
+        self.database
+            .link_store
+            .as_deref()
+            .is_some_and(|url| url != "memory")
+    }
+}
+
+// Unset or empty leaves the file's value; unparsable names the variable.
+fn env<T>(
+    name: &str,
+    slot: &mut T,
+    parse: impl Fn(&str) -> Result<T, String>,
+) -> anyhow::Result<()> {
+    match std::env::var(name) {
+        Ok(raw) if !raw.trim().is_empty() => {
+            *slot = parse(raw.trim()).map_err(|err| anyhow::anyhow!("{name}: {err}"))?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn text(raw: &str) -> Result<String, String> {
+    Ok(raw.to_string())
+}
+
+fn optional(raw: &str) -> Result<Option<String>, String> {
+    Ok(Some(raw.to_string()))
+}
+
+fn path(raw: &str) -> Result<Option<PathBuf>, String> {
+    Ok(Some(PathBuf::from(raw)))
+}
+
+fn list(raw: &str) -> Result<Vec<String>, String> {
+    Ok(raw
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect())
+}
+
+fn number<T: std::str::FromStr>(raw: &str) -> Result<T, String> {
+    raw.parse().map_err(|_| format!("not a number: {raw}"))
+}
+
+// Also parses on/off query and form fields, so they read like the config.
+pub fn flag(raw: &str) -> Result<bool, String> {
+    match raw.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" => Ok(false),
+        other => Err(format!("expected true or false, got {other}")),
+    }
+}
+
+fn bytes(raw: &str) -> Result<usize, String> {
+    if raw.eq_ignore_ascii_case("off") {
+        return Ok(0);
+    }
+    number(raw)
+}
+
+fn store(raw: &str) -> Result<Store, String> {
+    match raw.to_ascii_lowercase().as_str() {
+        "sqlite" => Ok(Store::Sqlite),
+        "disk" => Ok(Store::Disk),
+        other => Err(format!("expected sqlite or disk, got {other}")),
+    }
+}
+
+fn signet_mode(raw: &str) -> Result<SignetMode, String> {
+    match raw.to_ascii_lowercase().as_str() {
+        "free" => Ok(SignetMode::Free),
+        "grapheme" => Ok(SignetMode::Grapheme),
+        "registry" => Ok(SignetMode::Registry),
+        other => Err(format!("expected free, grapheme or registry, got {other}")),
+    }
+}
+
+// TOML side of the same grammars: "7d" or 604800, "30/1m" or "off",
+// 5242880 or "off".
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NumOrText {
+    Num(i64),
+    Text(String),
+}
+
+fn de_duration<'de, D: Deserializer<'de>>(de: D) -> Result<i64, D::Error> {
+    match NumOrText::deserialize(de)? {
+        NumOrText::Num(secs) if secs > 0 => Ok(secs),
+        NumOrText::Num(secs) => Err(serde::de::Error::custom(format!(
+            "duration must be positive: {secs}"
+        ))),
+        NumOrText::Text(raw) => ttl::parse_duration(&raw).map_err(serde::de::Error::custom),
+    }
+}
+
+fn de_rate<'de, D: Deserializer<'de>>(de: D) -> Result<Option<ratelimit::Rule>, D::Error> {
+    match NumOrText::deserialize(de)? {
+        NumOrText::Num(0) => Ok(None),
+        NumOrText::Num(n) => Err(serde::de::Error::custom(format!(
+            "expected <count>/<period> or off, got {n}"
+        ))),
+        NumOrText::Text(raw) => ratelimit::Rule::parse(&raw).map_err(serde::de::Error::custom),
+    }
+}
+
+fn de_bytes<'de, D: Deserializer<'de>>(de: D) -> Result<usize, D::Error> {
+    match NumOrText::deserialize(de)? {
+        NumOrText::Num(n) => usize::try_from(n).map_err(|_| {
+            serde::de::Error::custom(format!("expected a size in bytes or off, got {n}"))
+        }),
+        NumOrText::Text(raw) => bytes(raw.trim()).map_err(serde::de::Error::custom),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The file half of load: no env, so the tests don't see the shell's.
+    fn parse(text: &str) -> anyhow::Result<Config> {
+        let cfg: Config = toml::from_str(text)?;
+        cfg.validate()?;
+        Ok(cfg)
+    }
+
+    fn rule(rule: Option<ratelimit::Rule>) -> Option<(u32, u64)> {
+        rule.map(|rule| (rule.burst, rule.period.as_secs()))
+    }
+
+    // validate names the dotted key; a TOML error quotes the line instead.
+    #[test]
+    fn bad_configs_name_their_key() {
+        for (text, key) in [
+            (
+                "[links]\ndefault_ttl = \"30d\"\nmax_ttl = \"7d\"",
+                "links.default_ttl",
+            ),
+            ("[links]\ndefault_ttl = -5", "default_ttl = "),
+            ("[links]\nmax_ttl = \"forever\"", "max_ttl = "),
+            ("[links]\nslug_length = 3", "links.slug_length"),
+            ("[links]\nslug_length = 17", "links.slug_length"),
+            (
+                "[webhooks]\nurls = [\"https://hooks.example/a\"]",
+                "webhooks.urls",
+            ),
+            (
+                "[webhooks]\nurls = [\"ftp://hooks.example\"]\nsecret = \"s\"",
+                "webhooks.urls",
+            ),
+            (
+                "[database]\nlink_store = \"postgres://db/amigo\"",
+                "attachments.max_bytes",
+            ),
+            (
+                "[database]\nlink_store = \"mysql://db\"",
+                "database.link_store",
+            ),
+            ("[links]\nmax_tll = \"7d\"", "max_tll"),
+            ("bogus = 1", "bogus"),
+            ("[rate_limit]\ndispatch = 30", "dispatch = "),
+            ("[rate_limit]\njoint = \"lots\"", "joint = "),
+            ("[attachments]\nmax_bytes = -1", "max_bytes = "),
+            ("[attachments]\nmax_bytes = \"5MB\"", "max_bytes = "),
+        ] {
+            let Err(err) = parse(text) else {
+                panic!("{text:?} loaded");
+            };
+            assert!(err.to_string().contains(key), "{text:?}: {err}");
+        }
+    }
+
+    #[test]
+    fn good_configs_load() {
+        for text in [
+            "",
+            "[links]\nslug_length = 4",
+            "[links]\nslug_length = 16\ndefault_ttl = \"365d\"\nmax_ttl = \"365d\"",
+            "[webhooks]\nurls = [\"https://hooks.example/a\"]\nsecret = \"s\"",
+            "[database]\nlink_store = \"postgres://db/amigo\"\n[attachments]\nmax_bytes = \"off\"",
+            "[database]\nlink_store = \"memory\"",
+        ] {
+            if let Err(err) = parse(text) {
+                panic!("{text:?}: {err}");
+            }
+        }
+    }
+
+    #[test]
+    fn toml_values_take_numbers_or_words() {
+        let cfg = parse(
+            "[links]\ndefault_ttl = 3600\nmax_ttl = \"30d\"\n\
+             [rate_limit]\ndispatch = \"off\"\njoint = \"5/10s\"\n\
+             [attachments]\nmax_bytes = 1024",
+        )
+        .unwrap();
+        assert_eq!(cfg.links.default_ttl, 3600);
+        assert_eq!(cfg.links.max_ttl, 30 * 24 * 3600);
+        assert_eq!(rule(cfg.rate_limit.dispatch), None);
+        assert_eq!(rule(cfg.rate_limit.joint), Some((5, 10)));
+        assert_eq!(cfg.attachments.max_bytes, 1024);
+
+        let cfg = parse(
+            "[rate_limit]\ndispatch = 0\njoint = \"0/1m\"\n\
+             [attachments]\nmax_bytes = \" OFF \"",
+        )
+        .unwrap();
+        assert_eq!(rule(cfg.rate_limit.dispatch), None);
+        assert_eq!(rule(cfg.rate_limit.joint), None);
+        assert_eq!(cfg.attachments.max_bytes, 0);
+
+        let defaults = parse("").unwrap();
+        assert_eq!(rule(defaults.rate_limit.dispatch), Some((30, 60)));
+        assert_eq!(defaults.attachments.max_bytes, 5 * 1024 * 1024);
+    }
+
+    #[test]
+    fn env_values_take_the_same_words() {
+        for (raw, want) in [("off", Some(0)), ("OFF", Some(0)), ("1024", Some(1024))] {
+            assert_eq!(bytes(raw).ok(), want, "{raw}");
+        }
+        assert!(bytes("-1").is_err());
+        assert!(bytes("5MB").is_err());
+        for raw in ["on", " Yes ", "TRUE", "1"] {
+            assert_eq!(flag(raw), Ok(true), "{raw}");
+        }
+        for raw in ["off", "no", "false", "0"] {
+            assert_eq!(flag(raw), Ok(false), "{raw}");
+        }
+        assert!(flag("maybe").is_err());
+
+        // Only this test touches these variables.
+        std::env::set_var("RATE_LIMIT_DISPATCH", "off");
+        std::env::set_var("RATE_LIMIT_JOINT", "5/1s");
+        std::env::set_var("ATTACHMENT_MAX_BYTES", "off");
+        let mut cfg = Config::default();
+        let applied = cfg.apply_env();
+        std::env::set_var("ATTACHMENT_MAX_BYTES", "lots");
+        let broken = Config::default().apply_env();
+        for name in [
+            "RATE_LIMIT_DISPATCH",
+            "RATE_LIMIT_JOINT",
+            "ATTACHMENT_MAX_BYTES",
+        ] {
+            std::env::remove_var(name);
+        }
+
+        applied.unwrap();
+        assert_eq!(rule(cfg.rate_limit.dispatch), None);
+        assert_eq!(rule(cfg.rate_limit.joint), Some((5, 1)));
+        assert_eq!(cfg.attachments.max_bytes, 0);
+        let Err(err) = broken else {
+            panic!("ATTACHMENT_MAX_BYTES=lots applied");
+        };
+        assert!(
+            err.to_string().starts_with("ATTACHMENT_MAX_BYTES:"),
+            "{err}"
+        );
+    }
+}
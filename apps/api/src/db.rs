@@ -1,9 +1,18 @@
 use anyhow::Result;
-use sqlx::{sqlite::SqlitePoolOptions, Row, SqlitePool};
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+    SqlitePool,
+};
 use std::fs::OpenOptions;
 use std::path::Path;
+use std::str::FromStr;
 
-pub async fn connect(database_url: &str) -> Result<SqlitePool> {
+use crate::config::DatabaseConfig;
+
+// `secure_delete` makes SQLite zero freed pages, so burned / purged notes
+// don't linger in the file. The schema is migrate::run's job.
+pub async fn connect(cfg: &DatabaseConfig) -> Result<SqlitePool> {
+    let database_url = cfg.url.as_str();
     // Ensure parent directory exists for file-based SQLite
     if let Some(file_path) = database_url.strip_prefix("sqlite:") {
         if file_path != ":memory:" && !file_path.is_empty() {
@@ -12,300 +21,250 @@ pub async fn connect(database_url: &str) -> Result<SqlitePool> {
                 std::fs::create_dir_all(parent)?;
             }
             // Touch the DB file so SQLite can open it in restrictive envs.
-            OpenOptions::new().create(true).write(true).open(path)?;
+            OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .open(path)?;
         }
     }
 
+    let mut options = SqliteConnectOptions::from_str(database_url)?;
+    if cfg.secure_delete {
+        options = options.pragma("secure_delete", "ON");
+    }
+
     let pool = SqlitePoolOptions::new()
-        .max_connections(5)
-        .connect(database_url)
+        .max_connections(cfg.max_connections)
+        .connect_with(options)
         .await?;
 
-    ensure_schema(&pool).await?;
-
     Ok(pool)
 }
 
-async fn ensure_schema(pool: &SqlitePool) -> Result<()> {
-    let columns = sqlx::query("PRAGMA table_info(links)")
-        .fetch_all(pool)
-        .await?;
-
-    if columns.is_empty() {
-        create_links_table(pool).await?;
-        return Ok(());
-    }
-
-    let mut url_notnull = false;
-    let mut has_views = false;
-    let mut has_max_views = false;
-    let mut has_reply_to = false;
-    let mut has_emoji = false;
-
-    for row in columns {
-        let name: String = row.try_get("name")?;
-        let notnull: i64 = row.try_get("notnull")?;
-        if name == "url" {
-            url_notnull = notnull == 1;
-        }
-        if name == "views" {
-            has_views = true;
-        }
-        if name == "max_views" {
-            has_max_views = true;
-        }
-        if name == "reply_to" {
-            has_reply_to = true;
-        }
-        if name == "emoji" {
-            has_emoji = true;
-        }
-    }
-
-    if url_notnull || !has_views || !has_max_views {
-        rebuild_links_table(pool, has_views, has_max_views, has_reply_to, has_emoji).await?;
-    } else {
-        if !has_reply_to {
-            ensure_reply_to_column(pool).await?;
-        }
-        if !has_emoji {
-            ensure_emoji_column(pool).await?;
-        }
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_links_expiry ON links(expires_at)")
-            .execute(pool)
-            .await?;
-    }
-
+pub async fn vacuum(pool: &SqlitePool) -> Result<()> {
+    sqlx::query("VACUUM").execute(pool).await?;
     Ok(())
 }
 
-async fn create_links_table(pool: &SqlitePool) -> Result<()> {
+#[derive(sqlx::FromRow)]
+pub struct Delivery {
+    pub id: i64,
+    pub url: String,
+    pub secret: Option<String>,
+    pub event: String,
+    pub payload: String,
+    pub attempts: i64,
+}
+
+pub async fn enqueue_delivery(
+    pool: &SqlitePool,
+    url: &str,
+    secret: Option<&str>,
+    event: &str,
+    payload: &str,
+    now: i64,
+) -> Result<()> {
     sqlx::query(
         r#"
-        CREATE TABLE IF NOT EXISTS links (
-          slug TEXT PRIMARY KEY,
-          url TEXT,
-          note TEXT,
-          created_at INTEGER NOT NULL,
-          expires_at INTEGER,
-          views INTEGER DEFAULT 0,
-          max_views INTEGER,
-          reply_to TEXT,
-          emoji TEXT
-        )
+        INSERT INTO webhook_deliveries (url, secret, event, payload, next_attempt_at, created_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?5)
         "#,
     )
+    .bind(url)
+    .bind(secret)
+    .bind(event)
+    .bind(payload)
+    .bind(now)
     .execute(pool)
     .await?;
-
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_links_expiry ON links(expires_at)")
-        .execute(pool)
-        .await?;
-
     Ok(())
 }
 
-async fn rebuild_links_table(
-    pool: &SqlitePool,
-    has_views: bool,
-    has_max_views: bool,
-    has_reply_to: bool,
-    has_emoji: bool,
-) -> Result<()> {
-    let views_expr = if has_views { "views" } else { "0" };
-    let max_views_expr = if has_max_views { "max_views" } else { "NULL" };
-    let reply_expr = if has_reply_to { "reply_to" } else { "NULL" };
-    let emoji_expr = if has_emoji { "emoji" } else { "NULL" };
-    let insert_sql = format!(
-        "INSERT INTO links_new (slug, url, note, created_at, expires_at, views, max_views, reply_to, emoji) \
-         SELECT slug, url, note, created_at, expires_at, {views_expr}, {max_views_expr}, {reply_expr}, {emoji_expr} \
-         FROM links"
-    );
-
-    let mut tx = pool.begin().await?;
-    sqlx::query(
+pub async fn due_deliveries(pool: &SqlitePool, now: i64, limit: i64) -> Result<Vec<Delivery>> {
+    let rows = sqlx::query_as::<_, Delivery>(
         r#"
-        CREATE TABLE links_new (
-          slug TEXT PRIMARY KEY,
-          url TEXT,
-          note TEXT,
-          created_at INTEGER NOT NULL,
-          expires_at INTEGER,
-          views INTEGER DEFAULT 0,
-          max_views INTEGER,
-          reply_to TEXT,
-          emoji TEXT
-        )
+        SELECT id, url, secret, event, payload, attempts
+        FROM webhook_deliveries
+        WHERE next_attempt_at <= ?1
+        ORDER BY next_attempt_at, id
+        LIMIT ?2
         "#,
     )
-    .execute(&mut *tx)
+    .bind(now)
+    .bind(limit)
+    .fetch_all(pool)
     .await?;
+    Ok(rows)
+}
 
-    sqlx::query(&insert_sql).execute(&mut *tx).await?;
-    sqlx::query("DROP TABLE links").execute(&mut *tx).await?;
-    sqlx::query("ALTER TABLE links_new RENAME TO links")
-        .execute(&mut *tx)
-        .await?;
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_links_expiry ON links(expires_at)")
-        .execute(&mut *tx)
+// Delivered, or out of retries: either way it leaves the queue.
+pub async fn drop_delivery(pool: &SqlitePool, id: i64) -> Result<()> {
+    sqlx::query("DELETE FROM webhook_deliveries WHERE id = ?1")
+        .bind(id)
+        .execute(pool)
         .await?;
-    tx.commit().await?;
-
     Ok(())
 }
 
-async fn ensure_reply_to_column(pool: &SqlitePool) -> Result<()> {
-    let res = sqlx::query("ALTER TABLE links ADD COLUMN reply_to TEXT")
-        .execute(pool)
-        .await;
-
-    if let Err(err) = res {
-        let msg = err.to_string();
-        if msg.contains("duplicate column") || msg.contains("already exists") {
-            return Ok(());
-        }
-        return Err(err.into());
-    }
-
+pub async fn retry_delivery(pool: &SqlitePool, id: i64, next_at: i64, error: &str) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE webhook_deliveries
+        SET attempts = attempts + 1, next_attempt_at = ?2, last_error = ?3
+        WHERE id = ?1
+        "#,
+    )
+    .bind(id)
+    .bind(next_at)
+    .bind(error)
+    .execute(pool)
+    .await?;
     Ok(())
 }
 
-async fn ensure_emoji_column(pool: &SqlitePool) -> Result<()> {
-    let res = sqlx::query("ALTER TABLE links ADD COLUMN emoji TEXT")
-        .execute(pool)
-        .await;
-
-    if let Err(err) = res {
-        let msg = err.to_string();
-        if msg.contains("duplicate column") || msg.contains("already exists") {
-            return Ok(());
-        }
-        return Err(err.into());
-    }
-
-    Ok(())
+pub struct NewAttachment<'a> {
+    pub callsign: &'a str,
+    pub slug: &'a str,
+    pub content_type: &'a str,
+    pub filename: Option<&'a str>,
+    pub size: i64,
+    pub data: Option<&'a [u8]>,
+    pub file: Option<&'a str>,
+    pub created_at: i64,
 }
 
-pub async fn insert_link(
-    pool: &SqlitePool,
-    slug: &str,
-    url: Option<&str>,
-    note: Option<&str>,
-    created_at: i64,
-    expires_at: Option<i64>,
-    max_views: Option<i64>,
-    reply_to: Option<&str>,
-    emoji: Option<&str>,
-) -> Result<()> {
+pub async fn insert_attachment(pool: &SqlitePool, attachment: &NewAttachment<'_>) -> Result<()> {
     sqlx::query(
         r#"
-        INSERT INTO links (slug, url, note, created_at, expires_at, max_views, reply_to, emoji)
+        INSERT INTO attachments (callsign, slug, content_type, filename, size, data, file, created_at)
         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
         "#,
     )
-    .bind(slug)
-    .bind(url)
-    .bind(note)
-    .bind(created_at)
-    .bind(expires_at)
-    .bind(max_views)
-    .bind(reply_to)
-    .bind(emoji)
+    .bind(attachment.callsign)
+    .bind(attachment.slug)
+    .bind(attachment.content_type)
+    .bind(attachment.filename)
+    .bind(attachment.size)
+    .bind(attachment.data)
+    .bind(attachment.file)
+    .bind(attachment.created_at)
     .execute(pool)
     .await?;
     Ok(())
 }
 
-pub struct LinkRow {
-    pub url: Option<String>,
-    pub note: Option<String>,
-    pub expires_at: Option<i64>,
-    pub reply_to: Option<String>,
-    pub emoji: Option<String>,
+#[derive(sqlx::FromRow)]
+pub struct AttachmentMeta {
+    pub id: i64,
+    pub content_type: String,
+    pub filename: Option<String>,
+    pub size: i64,
 }
 
-pub async fn get_link(pool: &SqlitePool, slug: &str, now: i64) -> Result<Option<LinkRow>> {
-    // Atomic expiry check in DB (Toni Capone style: Contract of Truth)
-    // Read-only: No views increment here (moved to commence endpoint)
-    let row = sqlx::query_as::<_, (Option<String>, Option<String>, Option<i64>, Option<String>, Option<String>)>(
-        r#"
-        SELECT url, note, expires_at, reply_to, emoji
-        FROM links
-        WHERE slug = ?1
-          AND (expires_at IS NULL OR expires_at > ?2)
-          AND (max_views IS NULL OR views < max_views)
-        "#,
+pub async fn attachment_meta(
+    pool: &SqlitePool,
+    ns: &str,
+    slug: &str,
+) -> Result<Option<AttachmentMeta>> {
+    let row = sqlx::query_as::<_, AttachmentMeta>(
+        "SELECT id, content_type, filename, size FROM attachments WHERE callsign = ?1 AND slug = ?2",
     )
+    .bind(ns)
     .bind(slug)
-    .bind(now)
     .fetch_optional(pool)
     .await?;
+    Ok(row)
+}
 
-    if let Some((url, note, expires_at, reply_to, emoji)) = row {
-        return Ok(Some(LinkRow {
-            url,
-            note,
-            expires_at,
-            reply_to,
-            emoji,
-        }));
-    }
-
-    Ok(None)
+#[derive(sqlx::FromRow)]
+pub struct StoredAttachment {
+    pub content_type: String,
+    pub filename: Option<String>,
+    pub data: Option<Vec<u8>>,
+    pub file: Option<String>,
+    pub released: bool,
 }
 
-// Proof of Breath: Increment views when user actually commences journey
-pub async fn commence_journey(pool: &SqlitePool, slug: &str, now: i64) -> Result<bool> {
-    let mut tx = pool.begin().await?;
-    let row = sqlx::query_as::<_, (i64, Option<i64>)>(
+pub async fn load_attachment(pool: &SqlitePool, id: i64) -> Result<Option<StoredAttachment>> {
+    let row = sqlx::query_as::<_, StoredAttachment>(
         r#"
-        SELECT COALESCE(views, 0), max_views
-        FROM links
-        WHERE slug = ?1
-          AND (expires_at IS NULL OR expires_at > ?2)
-          AND (max_views IS NULL OR views < max_views)
+        SELECT content_type, filename, data, file, released_at IS NOT NULL AS released
+        FROM attachments WHERE id = ?1
         "#,
     )
-    .bind(slug)
-    .bind(now)
-    .fetch_optional(&mut *tx)
+    .bind(id)
+    .fetch_optional(pool)
     .await?;
+    Ok(row)
+}
 
-    let Some((views, max_views)) = row else {
-        return Ok(false);
-    };
+// Outer None = nothing stored; inner = the file to unlink (disk store).
+pub async fn delete_attachment(
+    pool: &SqlitePool,
+    ns: &str,
+    slug: &str,
+) -> Result<Option<Option<String>>> {
+    let row = sqlx::query_as::<_, (Option<String>,)>(
+        "DELETE FROM attachments WHERE callsign = ?1 AND slug = ?2 RETURNING file",
+    )
+    .bind(ns)
+    .bind(slug)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|(file,)| file))
+}
 
-    if let Some(limit) = max_views {
-        if views + 1 >= limit {
-            sqlx::query("DELETE FROM links WHERE slug = ?1")
-                .bind(slug)
-                .execute(&mut *tx)
-                .await?;
-            tx.commit().await?;
-            return Ok(true);
-        }
-    }
+pub async fn delete_attachment_id(pool: &SqlitePool, id: i64) -> Result<Option<Option<String>>> {
+    let row = sqlx::query_as::<_, (Option<String>,)>(
+        "DELETE FROM attachments WHERE id = ?1 RETURNING file",
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|(file,)| file))
+}
 
+// Its link was consumed: the file waits only for the download that last
+// commence granted.
+pub async fn release_attachment(pool: &SqlitePool, ns: &str, slug: &str, now: i64) -> Result<()> {
     sqlx::query(
-        r#"
-        UPDATE links
-        SET views = views + 1
-        WHERE slug = ?1
-        "#,
+        "UPDATE attachments SET released_at = ?3 \
+         WHERE callsign = ?1 AND slug = ?2 AND released_at IS NULL",
     )
+    .bind(ns)
     .bind(slug)
-    .execute(&mut *tx)
+    .bind(now)
+    .execute(pool)
     .await?;
-    tx.commit().await?;
+    Ok(())
+}
 
-    Ok(true)
+// Released before the cutoff.
+pub async fn purge_attachments(
+    pool: &SqlitePool,
+    released_before: i64,
+) -> Result<Vec<Option<String>>> {
+    let rows = sqlx::query_as::<_, (Option<String>,)>(
+        "DELETE FROM attachments WHERE released_at IS NOT NULL AND released_at <= ?1 RETURNING file",
+    )
+    .bind(released_before)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(|(file,)| file).collect())
 }
 
-// Absolute burn: hard delete regardless of url/max_views.
-pub async fn burn_link(pool: &SqlitePool, slug: &str) -> Result<bool> {
-    let res = sqlx::query("DELETE FROM links WHERE slug = ?1")
-        .bind(slug)
-        .execute(pool)
-        .await?;
-    Ok(res.rows_affected() > 0)
+// Unreleased files older than the cutoff, as (id, callsign, slug), for the
+// orphan check against the link store.
+pub async fn held_attachments(
+    pool: &SqlitePool,
+    created_before: i64,
+) -> Result<Vec<(i64, String, String)>> {
+    let rows = sqlx::query_as::<_, (i64, String, String)>(
+        "SELECT id, callsign, slug FROM attachments WHERE released_at IS NULL AND created_at <= ?1",
+    )
+    .bind(created_before)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
 }
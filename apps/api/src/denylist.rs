@@ -0,0 +1,357 @@
+use regex::Regex;
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    sync::{Arc, Mutex, RwLock},
+    time::SystemTime,
+};
+
+use crate::store::LinkStore;
+
+// Destination denylist, one rule per line ('#' lines are comments):
+//
+//   evil.example          domain: the host and all its subdomains
+//   domain:evil.example   same, spelled out
+//   suffix:.zip           host ends with this (".zip", "-login.com")
+//   regex:^https?://[^/]*paypa1\.
+//                         matched against the whole normalized url
+#[derive(Default)]
+struct Rules {
+    domains: HashSet<String>,
+    suffixes: Vec<String>,
+    regexes: Vec<Regex>,
+}
+
+impl Rules {
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut rules = Self::default();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (kind, value) = match line.split_once(':') {
+                Some((kind @ ("domain" | "suffix" | "regex"), value)) => (kind, value.trim()),
+                _ => ("domain", line),
+            };
+            match kind {
+                "regex" => {
+                    let re = Regex::new(value)
+                        .map_err(|err| format!("line {}: bad regex: {err}", n + 1))?;
+                    rules.regexes.push(re);
+                }
+                _ => {
+                    let host = value.trim_end_matches('.').to_ascii_lowercase();
+                    let host = idna::domain_to_ascii(&host).unwrap_or(host);
+                    if host.is_empty() {
+                        return Err(format!("line {}: empty {kind}", n + 1));
+                    }
+                    if kind == "suffix" {
+                        rules.suffixes.push(host);
+                    } else {
+                        rules
+                            .domains
+                            .insert(host.trim_start_matches('.').to_string());
+                    }
+                }
+            }
+        }
+        Ok(rules)
+    }
+
+    fn len(&self) -> usize {
+        self.domains.len() + self.suffixes.len() + self.regexes.len()
+    }
+
+    fn check(&self, url: &str) -> Option<String> {
+        let host = url::Url::parse(url)
+            .ok()
+            .and_then(|url| {
+                url.host_str()
+                    .map(|host| host.trim_end_matches('.').to_string())
+            })
+            .unwrap_or_default();
+        if !host.is_empty() {
+            // evil.example also covers a.b.evil.example
+            let mut rest = host.as_str();
+            loop {
+                if self.domains.contains(rest) {
+                    return Some(format!("domain:{rest}"));
+                }
+                match rest.split_once('.') {
+                    Some((_, parent)) => rest = parent,
+                    None => break,
+                }
+            }
+            if let Some(suffix) = self.suffixes.iter().find(|s| host.ends_with(s.as_str())) {
+                return Some(format!("suffix:{suffix}"));
+            }
+        }
+        self.regexes
+            .iter()
+            .find(|re| re.is_match(url))
+            .map(|re| format!("regex:{}", re.as_str()))
+    }
+}
+
+// Loaded from denylist.path and re-read when the file's mtime changes. A
+// broken edit keeps the previous rules rather than opening the gates.
+pub struct Denylist {
+    path: Option<PathBuf>,
+    rules: RwLock<Arc<Rules>>,
+    mtime: Mutex<Option<SystemTime>>,
+}
+
+impl Denylist {
+    pub fn new(path: Option<PathBuf>) -> anyhow::Result<Self> {
+        let list = Self {
+            path,
+            rules: RwLock::new(Arc::new(Rules::default())),
+            mtime: Mutex::new(None),
+        };
+        if list.path.is_some() {
+            // A bad file at startup is a config error, not a warning.
+            list.reload()?;
+        }
+        Ok(list)
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.path.is_some()
+    }
+
+    // Some(rule) if the (normalized) url is listed.
+    pub fn check(&self, url: &str) -> Option<String> {
+        let rules = self.rules.read().unwrap().clone();
+        rules.check(url)
+    }
+
+    // Ok(true) when the file changed and the new rules are live.
+    pub fn reload_if_changed(&self) -> anyhow::Result<bool> {
+        let Some(path) = self.path.as_ref() else {
+            return Ok(false);
+        };
+        let modified = std::fs::metadata(path)?.modified()?;
+        {
+            let mut seen = self.mtime.lock().unwrap();
+            if *seen == Some(modified) {
+                return Ok(false);
+            }
+            // Remember the attempt so a broken file is reported once per edit.
+            *seen = Some(modified);
+        }
+        self.reload()?;
+        Ok(true)
+    }
+
+    // Retroactive denylist: mark links whose destination is now listed,
+    // clear the ones that were taken off the list.
+    pub async fn apply(&self, store: &dyn LinkStore) {
+        const BATCH: i64 = 500;
+        let (mut after, mut blocked, mut cleared) = ((String::new(), String::new()), 0u64, 0u64);
+        loop {
+            let rows = match store.list((&after.0, &after.1), BATCH).await {
+                Ok(rows) => rows,
+                Err(err) => {
+                    tracing::error!(error = ?err, "denylist scan failed");
+                    return;
+                }
+            };
+            let n = rows.len() as i64;
+            for row in rows {
+                let listed = self.check(&row.url);
+                if listed != row.blocked {
+                    if let Err(err) = store
+                        .set_blocked(&row.callsign, &row.slug, listed.as_deref())
+                        .await
+                    {
+                        tracing::error!(error = ?err, "denylist update failed");
+                        return;
+                    }
+                    if listed.is_some() {
+                        blocked += 1;
+                    } else {
+                        cleared += 1;
+                    }
+                }
+                after = (row.callsign, row.slug);
+            }
+            if n < BATCH {
+                break;
+            }
+            tokio::task::yield_now().await;
+        }
+        if blocked + cleared > 0 {
+            tracing::info!(blocked, cleared, "denylist applied to existing links");
+        }
+    }
+
+    fn reload(&self) -> anyhow::Result<()> {
+        let Some(path) = self.path.as_ref() else {
+            return Ok(());
+        };
+        let modified = std::fs::metadata(path)?.modified()?;
+        let text = std::fs::read_to_string(path)?;
+        let rules = Rules::parse(&text)
+            .map_err(|err| anyhow::anyhow!("denylist.path {}: {err}", path.display()))?;
+        tracing::info!(rules = rules.len(), path = %path.display(), "denylist loaded");
+        *self.rules.write().unwrap() = Arc::new(rules);
+        *self.mtime.lock().unwrap() = Some(modified);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{MemoryStore, NewLink};
+    use std::time::Duration;
+
+    const RULES: &str = "\
+# phishing
+evil.example
+domain:Bad.Example.
+suffix:.zip
+regex:^https?://[^/]*paypa1\\.
+BÜCHER.de
+";
+
+    fn temp_file(text: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("amigo-denylist-{}.txt", crate::token::gen_token()));
+        std::fs::write(&path, text).unwrap();
+        path
+    }
+
+    #[test]
+    fn parses_every_rule_kind() {
+        let rules = Rules::parse(RULES).unwrap();
+        assert_eq!(rules.len(), 5);
+        assert!(rules.domains.contains("evil.example"));
+        assert!(rules.domains.contains("bad.example"));
+        assert!(rules.domains.contains("xn--bcher-kva.de"));
+        assert_eq!(rules.suffixes, [".zip"]);
+        assert_eq!(rules.regexes.len(), 1);
+
+        for (text, err) in [
+            ("ok.example\n\nregex:(unclosed", "line 3: bad regex"),
+            ("domain:", "line 1: empty domain"),
+            ("# fine\nsuffix: .", "line 2: empty suffix"),
+        ] {
+            let got = Rules::parse(text).err().unwrap();
+            assert!(got.starts_with(err), "{text:?}: {got}");
+        }
+    }
+
+    #[test]
+    fn checks_hosts_suffixes_and_urls() {
+        let rules = Rules::parse(RULES).unwrap();
+        for (url, want) in [
+            ("https://evil.example/", Some("domain:evil.example")),
+            ("https://a.b.evil.example/x", Some("domain:evil.example")),
+            ("https://evil.example./", Some("domain:evil.example")),
+            ("https://bad.example/", Some("domain:bad.example")),
+            ("https://xn--bcher-kva.de/", Some("domain:xn--bcher-kva.de")),
+            ("https://files.zip/a", Some("suffix:.zip")),
+            (
+                "https://login.paypa1.com/",
+                Some("regex:^https?://[^/]*paypa1\\."),
+            ),
+            ("https://notevil.example/", None),
+            ("https://evil.example.org/", None),
+            ("https://example.com/evil.example", None),
+            ("https://paypal.com/", None),
+            ("https://zip.example/", None),
+        ] {
+            assert_eq!(rules.check(url).as_deref(), want, "{url}");
+        }
+    }
+
+    #[test]
+    fn broken_edit_keeps_the_old_rules() {
+        let path = temp_file("evil.example\n");
+        let list = Denylist::new(Some(path.clone())).unwrap();
+        assert!(list.check("https://evil.example/").is_some());
+        assert!(!list.reload_if_changed().unwrap());
+
+        std::fs::write(&path, "other.example\nregex:(\n").unwrap();
+        let later = SystemTime::now() + Duration::from_secs(5);
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        let err = list.reload_if_changed().unwrap_err();
+        assert!(err.to_string().contains("line 2"), "{err}");
+        assert!(list.check("https://evil.example/").is_some());
+        assert!(list.check("https://other.example/").is_none());
+        // Reported once per edit.
+        assert!(!list.reload_if_changed().unwrap());
+
+        std::fs::write(&path, "other.example\n").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(later + Duration::from_secs(5))
+            .unwrap();
+        assert!(list.reload_if_changed().unwrap());
+        assert!(list.check("https://evil.example/").is_none());
+        assert!(list.check("https://other.example/").is_some());
+        let _ = std::fs::remove_file(path);
+    }
+
+    fn link<'a>(slug: &'a str, url: Option<&'a str>) -> NewLink<'a> {
+        NewLink {
+            slug,
+            callsign: "",
+            url,
+            note: None,
+            created_at: 0,
+            expires_at: None,
+            max_views: None,
+            reply_to: None,
+            emoji: None,
+            owner_hash: None,
+            envelope: None,
+            password_hash: None,
+            not_before: None,
+            receipt_hash: None,
+            webhook_url: None,
+            webhook_secret: None,
+        }
+    }
+
+    async fn blocked(store: &MemoryStore, slug: &str) -> Option<String> {
+        store.get("", slug, 0).await.unwrap().unwrap().blocked
+    }
+
+    #[tokio::test]
+    async fn apply_blocks_and_clears_existing_links() {
+        let store = MemoryStore::new();
+        for (slug, url) in [
+            ("bad", Some("https://www.evil.example/")),
+            ("good", Some("https://example.com/")),
+            ("note", None),
+        ] {
+            store.insert(&link(slug, url)).await.unwrap();
+        }
+        store
+            .set_blocked("", "good", Some("domain:stale.example"))
+            .await
+            .unwrap();
+
+        let path = temp_file("evil.example\n");
+        let list = Denylist::new(Some(path.clone())).unwrap();
+        let _ = std::fs::remove_file(path);
+        list.apply(&store).await;
+
+        assert_eq!(
+            blocked(&store, "bad").await.as_deref(),
+            Some("domain:evil.example")
+        );
+        assert_eq!(blocked(&store, "good").await, None);
+        assert_eq!(blocked(&store, "note").await, None);
+    }
+}
@@ -0,0 +1,141 @@
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+use tokio::sync::RwLock;
+
+use crate::token;
+
+// Wrong guesses allowed per slug inside one window before we go quiet.
+const MAX_FAILURES: u32 = 5;
+const FAILURE_WINDOW: Duration = Duration::from_secs(15 * 60);
+pub const UNLOCK_TTL: Duration = Duration::from_secs(10 * 60);
+
+pub fn hash_password(password: &str) -> anyhow::Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|err| anyhow::anyhow!("argon2: {err}"))
+}
+
+pub fn verify_password(password: &str, phc: &str) -> bool {
+    let Ok(parsed) = PasswordHash::new(phc) else {
+        return false;
+    };
+    Argon2::default()
+        .verify_password(password.as_bytes(), &parsed)
+        .is_ok()
+}
+
+struct Failures {
+    count: u32,
+    window_start: Instant,
+}
+
+struct Unlock {
+    slug: String,
+    expires: Instant,
+}
+
+// Password gate: per-slug throttle + short-lived unlock tokens (in memory,
+// a restart simply asks for the password again).
+pub struct LockGate {
+    failures: RwLock<HashMap<String, Failures>>,
+    unlocks: RwLock<HashMap<String, Unlock>>,
+}
+
+impl LockGate {
+    pub fn new() -> Self {
+        Self {
+            failures: RwLock::new(HashMap::new()),
+            unlocks: RwLock::new(HashMap::new()),
+        }
+    }
+
+    // Books one attempt before the password is checked, so parallel
+    // guesses can't all slip in under the limit; Err(wait) while the slug
+    // is cooling down. A right guess clears the count (clear_failures).
+    pub async fn reserve(&self, slug: &str) -> Result<(), Duration> {
+        let mut failures = self.failures.write().await;
+        let entry = failures.entry(slug.to_string()).or_insert(Failures {
+            count: 0,
+            window_start: Instant::now(),
+        });
+        let elapsed = entry.window_start.elapsed();
+        if elapsed >= FAILURE_WINDOW {
+            entry.count = 0;
+            entry.window_start = Instant::now();
+        } else if entry.count >= MAX_FAILURES {
+            return Err(FAILURE_WINDOW - elapsed);
+        }
+        entry.count += 1;
+        Ok(())
+    }
+
+    pub async fn clear_failures(&self, slug: &str) {
+        self.failures.write().await.remove(slug);
+    }
+
+    pub async fn issue(&self, slug: &str) -> String {
+        let unlock_token = token::gen_token();
+        let mut unlocks = self.unlocks.write().await;
+        unlocks.insert(
+            token::hash_token(&unlock_token),
+            Unlock {
+                slug: slug.to_string(),
+                expires: Instant::now() + UNLOCK_TTL,
+            },
+        );
+        unlock_token
+    }
+
+    pub async fn check(&self, slug: &str, unlock_token: &str) -> bool {
+        let unlocks = self.unlocks.read().await;
+        unlocks
+            .get(&token::hash_token(unlock_token))
+            .is_some_and(|unlock| unlock.slug == slug && unlock.expires > Instant::now())
+    }
+
+    pub async fn cleanup(&self) {
+        let now = Instant::now();
+        self.unlocks
+            .write()
+            .await
+            .retain(|_, unlock| unlock.expires > now);
+        self.failures
+            .write()
+            .await
+            .retain(|_, entry| entry.window_start.elapsed() < FAILURE_WINDOW);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn parallel_guesses_share_one_budget() {
+        let gate = std::sync::Arc::new(LockGate::new());
+        let tries: Vec<_> = (0..4 * MAX_FAILURES)
+            .map(|_| {
+                let gate = gate.clone();
+                tokio::spawn(async move { gate.reserve("abc").await.is_ok() })
+            })
+            .collect();
+        let mut let_in = 0;
+        for t in tries {
+            let_in += t.await.unwrap() as u32;
+        }
+        assert_eq!(let_in, MAX_FAILURES);
+        assert!(gate.reserve("abc").await.is_err());
+        assert!(gate.reserve("other").await.is_ok());
+
+        gate.clear_failures("abc").await;
+        assert!(gate.reserve("abc").await.is_ok());
+    }
+}
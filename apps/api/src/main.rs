@@ -1,9 +1,14 @@
 use axum::{
-    extract::{Path, Query, State},
     extract::ws::{Message, WebSocket, WebSocketUpgrade},
+    extract::{ConnectInfo, DefaultBodyLimit, FromRequest, Multipart, Path, Query, Request, State},
+    handler::Handler,
     http::{header, HeaderMap, HeaderValue, Method, StatusCode},
-    response::{IntoResponse, Redirect},
-    routing::{delete, get, post},
+    middleware::{self, Next},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Redirect, Response,
+    },
+    routing::{delete, get, patch, post},
     Json, Router,
 };
 use futures_util::{SinkExt, StreamExt};
@@ -14,33 +19,83 @@ use std::{
     sync::{Arc, Mutex},
     time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
 use tokio::sync::{broadcast, RwLock};
 use tower_http::cors::{AllowOrigin, CorsLayer};
 use tower_http::trace::TraceLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+use callsign::LinkKey;
+use receipt::{ReceiptEvent, ReceiptHub, ReceiptKind, Subscription};
+use signet::DEFAULT_SIGNET;
+use store::LinkStore;
+
+mod attachment;
+mod callsign;
+mod config;
 mod db;
+mod denylist;
+mod lock;
+mod metrics;
+mod migrate;
+mod qr;
+mod ratelimit;
+mod receipt;
+mod registry;
+mod signet;
 mod slug;
-
-const DEFAULT_SIGNET: &str = "💖";
+mod store;
+mod token;
+mod tracking;
+mod ttl;
+mod urlcheck;
+mod webhook;
+
+const MAX_THREAD_DEPTH: usize = 64;
+const MAX_THREAD_ITEMS: usize = 256;
+const OWNER_TOKEN_HEADER: &str = "x-owner-token";
+const PASSWORD_HEADER: &str = "x-amigo-password";
+const UNLOCK_HEADER: &str = "x-unlock-token";
+const RECEIPT_HEADER: &str = "x-receipt-id";
+const WEBHOOK_SECRET_HEADER: &str = "x-webhook-secret";
+const ATTACHMENT_HEADER: &str = "x-attachment-token";
+const RECEIPT_WAIT_SECS: u64 = 25;
+const RECEIPT_MAX_WAIT_SECS: u64 = 60;
+const MAX_PASSWORD_LEN: usize = 256;
+const ENVELOPE_ALGS: &[&str] = &["aes-256-gcm", "xchacha20-poly1305"];
+const MAX_CIPHERTEXT_LEN: usize = 64 * 1024;
 
 #[derive(Clone)]
 struct AppState {
+    // Local SQLite: attachments and the webhook queue.
     pool: SqlitePool,
-    base_url: String,
-    web_base_url: String,
+    store: Arc<dyn LinkStore>,
+    config: Arc<config::Config>,
     joint: Arc<JointHub>,
+    locks: Arc<lock::LockGate>,
+    receipts: Arc<ReceiptHub>,
+    webhooks: Arc<webhook::Webhooks>,
+    limits: Arc<ratelimit::RateLimiter>,
+    url_policy: urlcheck::UrlPolicy,
+    denylist: Arc<denylist::Denylist>,
+    tracking: Arc<tracking::TrackingFilter>,
+    signets: Arc<signet::SignetRules>,
+    signet_policy: registry::SignetPolicy,
+    attachments: Arc<attachment::Attachments>,
+    metrics: Arc<metrics::Metrics>,
 }
 
 #[derive(Clone)]
 struct JointHub {
     rooms: Arc<RwLock<HashMap<String, Arc<JointRoom>>>>,
+    buffer: usize,
 }
 
 struct JointRoom {
     tx: broadcast::Sender<JointEvent>,
     last_activity: Mutex<Instant>,
     burned: std::sync::atomic::AtomicBool,
+    owner_hash: String,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -62,6 +117,7 @@ enum JointClientEvent {
 struct JointCreateResponse {
     id: String,
     url: String,
+    owner_token: String,
 }
 
 #[derive(Serialize)]
@@ -75,11 +131,52 @@ struct DispatchPayload {
     url: Option<String>,
     note: Option<String>,
     text: Option<String>,
-    ttl: Option<String>, // "7d" (optional)
+    ttl: Option<String>,        // "7d", "1d12h", "P1W" (optional)
+    expires_at: Option<String>, // RFC 3339 (exclusive with ttl)
     burn: Option<bool>,
     max_views: Option<i64>,
     reply_to: Option<String>,
+    reply_to_slug: Option<String>, // "slug" / "@crew/slug", always a link
+    emoji: Option<String>,
+    envelope: Option<NoteEnvelope>,
+    password: Option<String>,
+    slug: Option<String>,         // vanity slug (optional)
+    callsign: Option<String>,     // "@crew" (needs the callsign owner key as Bearer)
+    not_before: Option<String>,   // RFC 3339 opening time
+    opens_in: Option<String>,     // "3d" (same grammar as ttl)
+    webhook_url: Option<String>,  // lifecycle events POSTed here, signed
+    strip_tracking: Option<bool>, // false keeps utm_* & co. (server default: on)
+}
+
+// Owner edit. Absent = unchanged; "" clears note / re-detects emoji.
+#[derive(Deserialize)]
+struct LinkPatch {
+    url: Option<String>,
+    note: Option<String>,
     emoji: Option<String>,
+    ttl: Option<String>,
+    expires_at: Option<String>,
+    max_views: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct CallsignPayload {
+    name: String,
+}
+
+#[derive(Serialize)]
+struct CallsignResponse {
+    callsign: String,
+    owner_key: String,
+}
+
+// Zero-knowledge note: sealed in the browser, the key stays in the URL
+// fragment (#k=...) which never reaches us. We store and return it as-is.
+#[derive(Serialize, Deserialize)]
+struct NoteEnvelope {
+    alg: String,
+    nonce: String,
+    ciphertext: String,
 }
 
 #[derive(Serialize)]
@@ -87,6 +184,53 @@ struct DispatchResponse {
     short: String,
     original: Option<String>,
     note: Option<String>,
+    owner_token: String,
+    receipt_id: String,
+    // Only when webhook_url was given: HMAC key for its deliveries.
+    webhook_secret: Option<String>,
+    // Tracking params dropped from the url ("utm_source", "fbclid", ...).
+    stripped_params: Vec<String>,
+    attachment: Option<AttachmentInfo>,
+}
+
+// What rides along with a link; the bytes only after a commence.
+#[derive(Serialize)]
+struct AttachmentInfo {
+    content_type: String,
+    filename: Option<String>,
+    size: i64,
+}
+
+impl From<db::AttachmentMeta> for AttachmentInfo {
+    fn from(meta: db::AttachmentMeta) -> Self {
+        Self {
+            content_type: meta.content_type,
+            filename: meta.filename,
+            size: meta.size,
+        }
+    }
+}
+
+// Commence of a link with a file: one download, within GRANT_TTL.
+#[derive(Serialize)]
+struct AttachmentGrant {
+    token: String,
+    url: String,
+    expires_in: u64,
+    #[serde(flatten)]
+    info: AttachmentInfo,
+}
+
+#[derive(Serialize)]
+struct CommenceResponse {
+    attachment: AttachmentGrant,
+}
+
+// Long-poll answer for clients that can't hold an event stream open.
+#[derive(Serialize)]
+struct ReceiptPoll {
+    events: Vec<ReceiptEvent>,
+    finished: bool,
 }
 
 #[derive(Serialize)]
@@ -96,6 +240,8 @@ struct ResolveResponse {
     expires_at: Option<i64>,
     reply_to: Option<String>,
     emoji: Option<String>,
+    envelope: Option<NoteEnvelope>,
+    attachment: Option<AttachmentInfo>,
 }
 
 #[derive(Serialize)]
@@ -104,6 +250,41 @@ struct PeekResponse {
     gone: bool,
     emoji: Option<String>,
     has_url: bool,
+    locked: bool,
+    opens_at: Option<i64>,
+    blocked: bool,
+}
+
+#[derive(Serialize)]
+struct ThreadItem {
+    path: String,
+    reply_to: Option<String>,
+    emoji: Option<String>,
+    has_url: bool,
+    locked: bool,
+    created_at: i64,
+}
+
+#[derive(Serialize)]
+struct ThreadResponse {
+    root: String,
+    items: Vec<ThreadItem>,
+}
+
+#[derive(Serialize)]
+struct NotYetResponse {
+    opens_at: i64,
+}
+
+#[derive(Deserialize)]
+struct UnlockPayload {
+    password: String,
+}
+
+#[derive(Serialize)]
+struct UnlockResponse {
+    unlock_token: String,
+    expires_in: u64,
 }
 
 fn now_unix() -> i64 {
@@ -113,20 +294,111 @@ fn now_unix() -> i64 {
         .as_secs() as i64
 }
 
-fn parse_ttl(ttl: Option<&str>) -> Option<i64> {
-    // minimal: supports "7d", "24h", "30m" - return seconds
-    let ttl = ttl?;
-    if ttl.len() < 2 {
+fn parse_rfc3339(input: &str) -> Option<i64> {
+    OffsetDateTime::parse(input.trim(), &Rfc3339)
+        .ok()
+        .map(|dt| dt.unix_timestamp())
+}
+
+fn format_rfc3339(ts: i64) -> String {
+    OffsetDateTime::from_unix_timestamp(ts)
+        .ok()
+        .and_then(|dt| dt.format(&Rfc3339).ok())
+        .unwrap_or_else(|| ts.to_string())
+}
+
+// Scheduled link asked for too early: 425 + Retry-After until it opens.
+fn not_yet(opens_at: i64, now: i64) -> Response {
+    let mut h = HeaderMap::new();
+    if let Ok(value) = HeaderValue::from_str(&(opens_at - now).max(1).to_string()) {
+        h.insert(header::RETRY_AFTER, value);
+    }
+    (
+        StatusCode::TOO_EARLY,
+        h,
+        format!("Inca nu. Se deschide la {}.\n", format_rfc3339(opens_at)),
+    )
+        .into_response()
+}
+
+// Lifetime from `ttl` (duration, counted from `start`) or an absolute
+// RFC 3339 `expires_at`; links.default_ttl when neither. Capped at
+// links.max_ttl from `now`, not from `start`: a scheduled link's wait
+// counts too, or the row would sit on disk past max_ttl. Err carries the
+// 400 message.
+fn resolve_expiry(
+    ttl: Option<String>,
+    expires_at: Option<String>,
+    start: i64,
+    now: i64,
+    limits: &config::LinksConfig,
+) -> Result<i64, String> {
+    let expires_at = match (clean_opt(ttl), clean_opt(expires_at)) {
+        (Some(_), Some(_)) => return Err("ttl and expires_at are exclusive\n".to_string()),
+        (Some(ttl), None) => match ttl::parse_duration(&ttl) {
+            Ok(secs) => start.saturating_add(secs),
+            Err(err) => return Err(format!("invalid ttl: {err}\n")),
+        },
+        (None, Some(at)) => match parse_rfc3339(&at) {
+            Some(ts) if ts > start => ts,
+            Some(_) => return Err("expires_at must be in the future\n".to_string()),
+            None => return Err("invalid expires_at (want RFC 3339)\n".to_string()),
+        },
+        (None, None) => start + limits.default_ttl,
+    };
+    let max_ttl = limits.max_ttl;
+    if expires_at - now > max_ttl {
+        if start > now {
+            return Err(format!(
+                "opening delay plus ttl exceeds maximum of {max_ttl}s\n"
+            ));
+        }
+        return Err(format!("ttl exceeds maximum of {max_ttl}s\n"));
+    }
+    Ok(expires_at)
+}
+
+// reply_to names a link only in an explicit form: our short url, a
+// "/slug" path or "@crew/slug". A bare word ("thanks") is free text.
+fn reply_key(state: &AppState, raw: &str) -> Option<LinkKey> {
+    let base = state.config.server.base_url.trim_end_matches('/');
+    let path = raw.strip_prefix(base).unwrap_or(raw);
+    let path = path.split(['?', '#']).next().unwrap_or(path);
+    if !path.starts_with(['/', '@']) {
         return None;
     }
-    let (num, unit) = ttl.split_at(ttl.len() - 1);
-    let n: i64 = num.parse().ok()?;
-    match unit {
-        "d" => Some(n * 24 * 3600),
-        "h" => Some(n * 3600),
-        "m" => Some(n * 60),
-        _ => None,
+    LinkKey::parse(path)
+}
+
+fn blocked_url() -> urlcheck::UrlError {
+    urlcheck::UrlError::new("blocked", "destination is blocked on this server")
+}
+
+// Denylisted destination: no redirect, no view spent.
+fn blocked_response(headers: &HeaderMap) -> Response {
+    if is_cli_request(headers) {
+        return (StatusCode::FORBIDDEN, "Drum blocat.\n").into_response();
+    }
+    (
+        StatusCode::FORBIDDEN,
+        Json(serde_json::json!({ "blocked": true })),
+    )
+        .into_response()
+}
+
+// 400 for a url that failed urlcheck: one line for the CLI, JSON otherwise.
+fn bad_url(field: &str, err: urlcheck::UrlError, headers: &HeaderMap) -> Response {
+    if is_cli_request(headers) {
+        let body = format!("invalid {field}: {}\n", err.message);
+        return (StatusCode::BAD_REQUEST, body).into_response();
     }
+    let body = serde_json::json!({
+        "error": "invalid_url",
+        "field": field,
+        "code": err.code,
+        "message": err.message,
+    });
+    (StatusCode::BAD_REQUEST, Json(body)).into_response()
 }
 
 fn clean_opt(input: Option<String>) -> Option<String> {
@@ -140,25 +412,33 @@ fn clean_opt(input: Option<String>) -> Option<String> {
     })
 }
 
-fn sanitize_emoji(input: Option<String>) -> Option<String> {
-    let value = input?;
-    let trimmed = value.trim();
-    if trimmed.is_empty() {
-        return None;
+fn check_envelope(envelope: &NoteEnvelope) -> Result<(), &'static str> {
+    if !ENVELOPE_ALGS.contains(&envelope.alg.as_str()) {
+        return Err("unsupported envelope alg\n");
     }
-    if trimmed.len() > 32 {
-        return None;
+    let is_b64 = |value: &str| {
+        !value.is_empty()
+            && value
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'/' | b'-' | b'_' | b'='))
+    };
+    if !is_b64(&envelope.nonce) || envelope.nonce.len() > 64 {
+        return Err("invalid envelope nonce\n");
     }
-    if trimmed.chars().any(|c| c.is_control()) {
-        return None;
+    if !is_b64(&envelope.ciphertext) {
+        return Err("invalid envelope ciphertext\n");
+    }
+    if envelope.ciphertext.len() > MAX_CIPHERTEXT_LEN {
+        return Err("envelope too large\n");
     }
-    Some(trimmed.to_string())
+    Ok(())
 }
 
 impl JointHub {
-    fn new() -> Self {
+    fn new(buffer: usize) -> Self {
         Self {
             rooms: Arc::new(RwLock::new(HashMap::new())),
+            buffer,
         }
     }
 
@@ -167,12 +447,13 @@ impl JointHub {
         rooms.get(id).cloned()
     }
 
-    async fn create_room(&self, id: String) -> Arc<JointRoom> {
-        let (tx, _) = broadcast::channel(64);
+    async fn create_room(&self, id: String, owner_hash: String) -> Arc<JointRoom> {
+        let (tx, _) = broadcast::channel(self.buffer);
         let room = Arc::new(JointRoom {
             tx,
             last_activity: Mutex::new(Instant::now()),
             burned: std::sync::atomic::AtomicBool::new(false),
+            owner_hash,
         });
         let mut rooms = self.rooms.write().await;
         rooms.insert(id, room.clone());
@@ -213,85 +494,6 @@ impl JointHub {
     }
 }
 
-fn detect_vibe(url: &str) -> String {
-    let u = url.to_lowercase();
-
-    if u.contains("awb")
-        || u.contains("track")
-        || u.contains("tracking")
-        || u.contains("fancourier")
-        || u.contains("sameday")
-        || u.contains("dhl")
-        || u.contains("fedex")
-        || u.contains("ups")
-        || u.contains("gls")
-        || u.contains("dpd")
-    {
-        return "📦".to_string();
-    }
-
-    if u.contains(".pdf")
-        || u.contains("invoice")
-        || u.contains("factura")
-        || u.contains("anaf")
-        || u.contains("contract")
-        || u.contains("docs.google")
-        || u.contains("drive")
-    {
-        return "📜".to_string();
-    }
-
-    if u.contains("spotify")
-        || u.contains("music.youtube")
-        || u.contains("soundcloud")
-        || u.contains("bandcamp")
-    {
-        return "📻".to_string();
-    }
-
-    if u.contains("maps")
-        || u.contains("goo.gl/maps")
-        || u.contains("waze")
-        || u.contains("openstreetmap")
-    {
-        return "📍".to_string();
-    }
-
-    if u.contains("stripe")
-        || u.contains("checkout")
-        || u.contains("revolut")
-        || u.contains("paypal")
-        || u.contains("wise")
-    {
-        return "💳".to_string();
-    }
-
-    if u.contains("github")
-        || u.contains("gitlab")
-        || u.contains("vercel")
-        || u.contains("railway")
-        || u.contains("docs.")
-    {
-        return "🧑‍💻".to_string();
-    }
-
-    DEFAULT_SIGNET.to_string()
-}
-
-fn resolve_signet(url: Option<&str>, emoji: Option<String>) -> String {
-    if let Some(value) = emoji {
-        if !value.trim().is_empty() {
-            return value;
-        }
-    }
-
-    if let Some(link) = url {
-        return detect_vibe(link);
-    }
-
-    DEFAULT_SIGNET.to_string()
-}
-
 fn is_cli_request(headers: &HeaderMap) -> bool {
     let ua = headers
         .get(header::USER_AGENT)
@@ -314,12 +516,9 @@ fn is_cli_request(headers: &HeaderMap) -> bool {
     accept.contains("text/plain")
 }
 
-fn cors_layer() -> CorsLayer {
-    let raw = std::env::var("CORS_ALLOW_ORIGINS").unwrap_or_else(|_| {
-        "https://amigo.sh,http://localhost:3001,http://localhost:3000".to_string()
-    });
-    let origins: Vec<HeaderValue> = raw
-        .split(',')
+fn cors_layer(allowed: &[String]) -> CorsLayer {
+    let origins: Vec<HeaderValue> = allowed
+        .iter()
         .filter_map(|origin| HeaderValue::from_str(origin.trim()).ok())
         .collect();
 
@@ -331,33 +530,438 @@ fn cors_layer() -> CorsLayer {
 
     CorsLayer::new()
         .allow_origin(allow_origin)
-        .allow_methods([Method::GET, Method::POST, Method::DELETE, Method::OPTIONS])
-        .allow_headers([header::CONTENT_TYPE, header::ACCEPT, header::AUTHORIZATION])
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PATCH,
+            Method::DELETE,
+            Method::OPTIONS,
+        ])
+        .allow_headers([
+            header::CONTENT_TYPE,
+            header::ACCEPT,
+            header::AUTHORIZATION,
+            header::HeaderName::from_static(PASSWORD_HEADER),
+            header::HeaderName::from_static(UNLOCK_HEADER),
+            header::HeaderName::from_static(ATTACHMENT_HEADER),
+        ])
+        .expose_headers([
+            header::HeaderName::from_static(OWNER_TOKEN_HEADER),
+            header::HeaderName::from_static(RECEIPT_HEADER),
+            header::HeaderName::from_static(WEBHOOK_SECRET_HEADER),
+        ])
 }
 
-async fn dispatch_link(
+// Owner gate for burn / edit / stats: Authorization: Bearer <owner_token>.
+async fn authorize_owner(
+    state: &AppState,
+    key: &LinkKey,
+    headers: &HeaderMap,
+) -> Result<(), Response> {
+    let Some(presented) = token::bearer(headers) else {
+        return Err((StatusCode::UNAUTHORIZED, "owner token required\n").into_response());
+    };
+    match state.store.owner_hash(&key.ns, &key.slug).await {
+        Ok(Some(Some(hash))) if token::verify(presented, &hash) => Ok(()),
+        Ok(Some(_)) => Err((StatusCode::FORBIDDEN, "forbidden\n").into_response()),
+        Ok(None) => Err((StatusCode::NOT_FOUND, "not-found\n").into_response()),
+        Err(err) => {
+            tracing::error!(error = ?err, "owner lookup failed");
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "auth failed\n").into_response())
+        }
+    }
+}
+
+// Only the callsign owner may place links under it.
+async fn authorize_callsign(
+    state: &AppState,
+    ns: &str,
+    headers: &HeaderMap,
+) -> Result<(), Response> {
+    let Some(presented) = token::bearer(headers) else {
+        return Err((StatusCode::UNAUTHORIZED, "callsign key required\n").into_response());
+    };
+    match state.store.callsign_owner(ns).await {
+        Ok(Some(hash)) if token::verify(presented, &hash) => Ok(()),
+        Ok(Some(_)) => Err((StatusCode::FORBIDDEN, "forbidden\n").into_response()),
+        Ok(None) => Err((StatusCode::NOT_FOUND, "unknown callsign\n").into_response()),
+        Err(err) => {
+            tracing::error!(error = ?err, "callsign lookup failed");
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "auth failed\n").into_response())
+        }
+    }
+}
+
+// Password gate for locked links: a valid unlock token (header or ?unlock=)
+// or the password itself. Misses count towards the per-slug throttle.
+// `slug` here is the full link path (LinkKey::path) so namespaces never mix.
+async fn check_unlock(
+    state: &AppState,
+    slug: &str,
+    phc: &str,
+    headers: &HeaderMap,
+    unlock: Option<&str>,
+) -> Result<(), Response> {
+    let unlock = unlock.or_else(|| headers.get(UNLOCK_HEADER).and_then(|v| v.to_str().ok()));
+    if let Some(unlock) = unlock {
+        if state.locks.check(slug, unlock).await {
+            return Ok(());
+        }
+    }
+
+    let Some(password) = headers
+        .get(PASSWORD_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+    else {
+        return Err((StatusCode::UNAUTHORIZED, "locked\n").into_response());
+    };
+
+    verify_or_throttle(state, slug, phc, password).await
+}
+
+async fn verify_or_throttle(
+    state: &AppState,
+    slug: &str,
+    phc: &str,
+    password: String,
+) -> Result<(), Response> {
+    if let Err(wait) = state.locks.reserve(slug).await {
+        let mut h = HeaderMap::new();
+        if let Ok(value) = HeaderValue::from_str(&wait.as_secs().max(1).to_string()) {
+            h.insert(header::RETRY_AFTER, value);
+        }
+        return Err((StatusCode::TOO_MANY_REQUESTS, h, "too many attempts\n").into_response());
+    }
+
+    let phc = phc.to_string();
+    let ok = tokio::task::spawn_blocking(move || lock::verify_password(&password, &phc))
+        .await
+        .unwrap_or(false);
+
+    if ok {
+        state.locks.clear_failures(slug).await;
+        Ok(())
+    } else {
+        Err((StatusCode::UNAUTHORIZED, "wrong password\n").into_response())
+    }
+}
+
+// Token bucket in front of the routes that create things.
+async fn rate_limit(
+    State((state, scope)): State<(Arc<AppState>, ratelimit::Scope)>,
+    ConnectInfo(peer): ConnectInfo<std::net::SocketAddr>,
+    req: Request,
+    next: Next,
+) -> Response {
+    match state.limits.check(scope, peer, req.headers()).await {
+        Ok(()) => next.run(req).await,
+        Err(wait) => {
+            let mut h = HeaderMap::new();
+            let secs = wait.as_secs_f64().ceil().max(1.0) as u64;
+            if let Ok(value) = HeaderValue::from_str(&secs.to_string()) {
+                h.insert(header::RETRY_AFTER, value);
+            }
+            (StatusCode::TOO_MANY_REQUESTS, h, "slow down\n").into_response()
+        }
+    }
+}
+
+// JSON, or multipart/form-data when a file comes along: a `file` part plus
+// the usual fields, as text parts or one `payload` JSON part.
+async fn dispatch_handler(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
-    Json(payload): Json<DispatchPayload>,
-) -> impl IntoResponse {
-    let url = clean_opt(payload.url);
+    request: Request,
+) -> Response {
+    let multipart = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("multipart/form-data"));
+    if !multipart {
+        return match Json::<DispatchPayload>::from_request(request, &state).await {
+            Ok(Json(payload)) => dispatch_link(state, headers, payload, None).await,
+            Err(rejection) => rejection.into_response(),
+        };
+    }
+    if !state.attachments.enabled() {
+        return (StatusCode::BAD_REQUEST, "attachments are off\n").into_response();
+    }
+    let form = match Multipart::from_request(request, &state).await {
+        Ok(form) => form,
+        Err(rejection) => return rejection.into_response(),
+    };
+    match read_dispatch_form(&state, form).await {
+        Ok((payload, upload)) => dispatch_link(state, headers, payload, upload).await,
+        Err(denied) => denied,
+    }
+}
+
+async fn read_dispatch_form(
+    state: &AppState,
+    mut form: Multipart,
+) -> Result<(DispatchPayload, Option<attachment::Upload>), Response> {
+    let bad = |msg: &str| (StatusCode::BAD_REQUEST, format!("{msg}\n")).into_response();
+    let max = state.attachments.max_bytes();
+    let mut fields = serde_json::Map::new();
+    let mut upload = None;
+    loop {
+        let mut field = match form.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(err) if err.status() == StatusCode::PAYLOAD_TOO_LARGE => {
+                return Err(
+                    (StatusCode::PAYLOAD_TOO_LARGE, "attachment too large\n").into_response()
+                )
+            }
+            Err(_) => return Err(bad("invalid form")),
+        };
+        let name = field.name().unwrap_or_default().to_string();
+        if name == "file" {
+            if upload.is_some() {
+                return Err(bad("one file per link"));
+            }
+            let filename = field.file_name().and_then(attachment::clean_filename);
+            let mut bytes = Vec::new();
+            loop {
+                match field.chunk().await {
+                    Ok(Some(chunk)) => {
+                        if bytes.len() + chunk.len() > max {
+                            return Err((StatusCode::PAYLOAD_TOO_LARGE, "attachment too large\n")
+                                .into_response());
+                        }
+                        bytes.extend_from_slice(&chunk);
+                    }
+                    Ok(None) => break,
+                    Err(_) => return Err(bad("invalid form")),
+                }
+            }
+            if bytes.is_empty() {
+                continue;
+            }
+            let Some(content_type) = attachment::sniff(&bytes) else {
+                return Err((
+                    StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                    "attachment must be an image, a pdf or audio\n",
+                )
+                    .into_response());
+            };
+            upload = Some(attachment::Upload {
+                content_type,
+                filename,
+                bytes,
+            });
+            continue;
+        }
+        let Ok(text) = field.text().await else {
+            return Err(bad("invalid form"));
+        };
+        if name == "payload" {
+            match serde_json::from_str::<serde_json::Value>(&text) {
+                Ok(serde_json::Value::Object(map)) => fields.extend(map),
+                _ => return Err(bad("invalid payload")),
+            }
+            continue;
+        }
+        // Text parts are strings; the few typed fields are parsed here.
+        let value = match name.as_str() {
+            "max_views" => match text.trim().parse::<i64>() {
+                Ok(n) => serde_json::Value::from(n),
+                Err(_) => return Err(bad("invalid max_views")),
+            },
+            "burn" | "strip_tracking" => match config::flag(&text) {
+                Ok(on) => serde_json::Value::Bool(on),
+                Err(_) => return Err(bad(&format!("invalid {name}"))),
+            },
+            "envelope" => match serde_json::from_str(&text) {
+                Ok(value) => value,
+                Err(_) => return Err(bad("invalid envelope")),
+            },
+            _ => serde_json::Value::String(text),
+        };
+        fields.insert(name, value);
+    }
+    let payload = serde_json::from_value(serde_json::Value::Object(fields))
+        .map_err(|err| bad(&format!("invalid payload: {err}")))?;
+    Ok((payload, upload))
+}
+
+async fn dispatch_link(
+    state: Arc<AppState>,
+    headers: HeaderMap,
+    payload: DispatchPayload,
+    upload: Option<attachment::Upload>,
+) -> Response {
+    let url = match clean_opt(payload.url).map(|raw| state.url_policy.normalize(&raw)) {
+        Some(Ok(url)) => Some(url),
+        Some(Err(err)) => return bad_url("url", err, &headers),
+        None => None,
+    };
+    let mut stripped_params = Vec::new();
+    let url = match url {
+        Some(url) if payload.strip_tracking.unwrap_or(state.tracking.enabled()) => {
+            let (clean, removed) = state.tracking.strip(&url);
+            stripped_params = removed;
+            Some(clean)
+        }
+        url => url,
+    };
+    if url
+        .as_deref()
+        .and_then(|url| state.denylist.check(url))
+        .is_some()
+    {
+        return bad_url("url", blocked_url(), &headers);
+    }
     let note = clean_opt(payload.note).or_else(|| clean_opt(payload.text));
-    let reply_to = clean_opt(payload.reply_to);
-    let emoji = resolve_signet(url.as_deref(), sanitize_emoji(payload.emoji));
+    // A reply_to that names a link (see reply_key) or a reply_to_slug must
+    // point at a live one; anything else stays free text.
+    let (text, parent) = match (
+        clean_opt(payload.reply_to),
+        clean_opt(payload.reply_to_slug),
+    ) {
+        (Some(_), Some(_)) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                "reply_to and reply_to_slug are exclusive\n",
+            )
+                .into_response()
+        }
+        (Some(raw), None) => match reply_key(&state, &raw) {
+            Some(parent) => (None, Some(parent)),
+            None => (Some(raw), None),
+        },
+        (None, Some(slug)) => match LinkKey::parse(&slug) {
+            Some(parent) => (None, Some(parent)),
+            None => return (StatusCode::BAD_REQUEST, "invalid reply_to_slug\n").into_response(),
+        },
+        (None, None) => (None, None),
+    };
+    let reply_to = match parent {
+        None => text,
+        Some(parent) => match state.store.get(&parent.ns, &parent.slug, now_unix()).await {
+            Ok(Some(_)) => Some(parent.path()),
+            Ok(None) => return (StatusCode::BAD_REQUEST, "reply_to not found\n").into_response(),
+            Err(_) => {
+                return (StatusCode::INTERNAL_SERVER_ERROR, "create failed\n").into_response()
+            }
+        },
+    };
+    // Signet detection only ever looks at the plaintext url; a sealed
+    // envelope is opaque to us and never reaches the signet rules.
+    let emoji = match state.signet_policy.check(payload.emoji) {
+        Ok(emoji) => state.signets.resolve(url.as_deref(), emoji),
+        Err(msg) => return (StatusCode::BAD_REQUEST, msg).into_response(),
+    };
+
+    let envelope = match payload.envelope {
+        Some(envelope) => {
+            if note.is_some() {
+                return (StatusCode::BAD_REQUEST, "note and envelope are exclusive\n")
+                    .into_response();
+            }
+            if let Err(msg) = check_envelope(&envelope) {
+                return (StatusCode::BAD_REQUEST, msg).into_response();
+            }
+            match serde_json::to_string(&envelope) {
+                Ok(raw) => Some(raw),
+                Err(_) => return (StatusCode::BAD_REQUEST, "invalid envelope\n").into_response(),
+            }
+        }
+        None => None,
+    };
 
-    if url.is_none() && note.is_none() {
+    if url.is_none() && note.is_none() && envelope.is_none() && upload.is_none() {
         return (StatusCode::BAD_REQUEST, "missing url or note\n").into_response();
     }
 
-    if let Some(ref value) = url {
-        if !(value.starts_with("http://") || value.starts_with("https://")) {
-            return (StatusCode::BAD_REQUEST, "invalid url\n").into_response();
+    let ns = match clean_opt(payload.callsign) {
+        Some(raw) => match callsign::namespace(&raw) {
+            Some(ns) => ns,
+            None => return (StatusCode::BAD_REQUEST, "invalid callsign\n").into_response(),
+        },
+        None => String::new(),
+    };
+    if !ns.is_empty() {
+        if let Err(denied) = authorize_callsign(&state, &ns, &headers).await {
+            return denied;
         }
     }
 
+    let vanity = match clean_opt(payload.slug).map(|value| slug::check_vanity(&value)) {
+        Some(Ok(value)) => Some(value),
+        Some(Err(msg)) => return (StatusCode::BAD_REQUEST, msg).into_response(),
+        None => None,
+    };
+
+    let password_hash = match payload.password.filter(|p| !p.is_empty()) {
+        Some(password) if password.len() > MAX_PASSWORD_LEN => {
+            return (StatusCode::BAD_REQUEST, "password too long\n").into_response();
+        }
+        Some(password) => {
+            match tokio::task::spawn_blocking(move || lock::hash_password(&password)).await {
+                Ok(Ok(phc)) => Some(phc),
+                _ => return (StatusCode::INTERNAL_SERVER_ERROR, "create failed\n").into_response(),
+            }
+        }
+        None => None,
+    };
+
+    let webhook_url =
+        match clean_opt(payload.webhook_url).map(|raw| state.url_policy.normalize(&raw)) {
+            Some(Ok(hook)) => Some(hook),
+            Some(Err(err)) => return bad_url("webhook_url", err, &headers),
+            None => None,
+        };
+    let webhook_secret = webhook_url.as_ref().map(|_| token::gen_token());
+
     let created_at = now_unix();
-    let ttl_secs = parse_ttl(payload.ttl.as_deref()).unwrap_or(7 * 24 * 3600);
-    let expires_at = Some(created_at + ttl_secs);
+    let not_before = match (clean_opt(payload.not_before), clean_opt(payload.opens_in)) {
+        (Some(_), Some(_)) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                "not_before and opens_in are exclusive\n",
+            )
+                .into_response();
+        }
+        (Some(at), None) => match parse_rfc3339(&at) {
+            Some(ts) => Some(ts),
+            None => return (StatusCode::BAD_REQUEST, "invalid not_before\n").into_response(),
+        },
+        (None, Some(delay)) => match ttl::parse_duration(&delay) {
+            Ok(secs) => Some(created_at.saturating_add(secs)),
+            Err(err) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    format!("invalid opens_in: {err}\n"),
+                )
+                    .into_response();
+            }
+        },
+        (None, None) => None,
+    }
+    .filter(|opens_at| *opens_at > created_at);
+    let max_ttl = state.config.links.max_ttl;
+    if not_before.is_some_and(|opens_at| opens_at - created_at >= max_ttl) {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("opening time must be less than {max_ttl}s away\n"),
+        )
+            .into_response();
+    }
+
+    // The ttl clock starts when the link opens, not when it was sent.
+    let start = not_before.unwrap_or(created_at);
+    let expires_at = match resolve_expiry(
+        payload.ttl,
+        payload.expires_at,
+        start,
+        created_at,
+        &state.config.links,
+    ) {
+        Ok(ts) => Some(ts),
+        Err(msg) => return (StatusCode::BAD_REQUEST, msg).into_response(),
+    };
     let mut max_views = payload.max_views;
 
     if payload.burn.unwrap_or(false) {
@@ -374,42 +978,135 @@ async fn dispatch_link(
         }
     }
 
+    let owner_token = token::gen_token();
+    let owner_hash = token::hash_token(&owner_token);
+    let receipt_id = token::gen_token();
+    let receipt_hash = token::hash_token(&receipt_id);
+
     let mut slug = String::new();
     let mut inserted = false;
     let mut last_err: Option<anyhow::Error> = None;
 
-    for attempt in 0..5 {
-        let len = if attempt == 0 { 6 } else { 7 };
-        slug = slug::gen_slug(len);
-        match db::insert_link(
-            &state.pool,
-            &slug,
-            url.as_deref(),
-            note.as_deref(),
+    // Vanity slugs get one shot, and a second one when the path was only
+    // held by an expired link the sweeper hasn't reached yet; random ones
+    // retry on collision.
+    let attempts = if vanity.is_some() { 2 } else { 5 };
+    for attempt in 0..attempts {
+        if attempt > 0 && vanity.is_none() {
+            state.metrics.inc("amigo_slug_retries_total", &[]);
+        }
+        slug = match vanity.as_ref() {
+            Some(value) => value.clone(),
+            None => slug::gen_slug(state.config.links.slug_length + usize::from(attempt > 0)),
+        };
+        let link = store::NewLink {
+            slug: &slug,
+            callsign: &ns,
+            url: url.as_deref(),
+            note: note.as_deref(),
             created_at,
             expires_at,
             max_views,
-            reply_to.as_deref(),
-            Some(emoji.as_str()),
-        )
-        .await
-        {
+            reply_to: reply_to.as_deref(),
+            emoji: Some(emoji.as_str()),
+            owner_hash: Some(owner_hash.as_str()),
+            envelope: envelope.as_deref(),
+            password_hash: password_hash.as_deref(),
+            not_before,
+            receipt_hash: Some(receipt_hash.as_str()),
+            webhook_url: webhook_url.as_deref(),
+            webhook_secret: webhook_secret.as_deref(),
+        };
+        match state.store.insert(&link).await {
             Ok(()) => {
                 inserted = true;
                 break;
             }
-            Err(err) => last_err = Some(err),
+            Err(err) if vanity.is_some() && attempt == 0 && store::is_conflict(&err) => {
+                last_err = Some(err);
+                match state.store.purge_path(&ns, &slug, created_at).await {
+                    Ok(Some(gone)) => expire_link(&state, &gone, created_at).await,
+                    Ok(None) => break,
+                    Err(err) => {
+                        last_err = Some(err);
+                        break;
+                    }
+                }
+            }
+            Err(err) => {
+                last_err = Some(err);
+                if vanity.is_some() {
+                    break;
+                }
+            }
         }
     }
 
     if !inserted {
         if let Some(err) = last_err {
+            if vanity.is_some() && store::is_conflict(&err) {
+                return (StatusCode::CONFLICT, "slug taken\n").into_response();
+            }
             tracing::error!(error = ?err, "failed to insert link");
         }
         return (StatusCode::INTERNAL_SERVER_ERROR, "create failed\n").into_response();
     }
 
-    let short_link = format!("{}/{}", state.base_url.trim_end_matches('/'), slug);
+    let attachment = match upload {
+        Some(upload) => {
+            if let Err(err) = state
+                .attachments
+                .save(&state.pool, &ns, &slug, &upload, created_at)
+                .await
+            {
+                tracing::error!(error = ?err, "failed to store attachment");
+                let _ = state.store.burn(&ns, &slug).await;
+                return (StatusCode::INTERNAL_SERVER_ERROR, "create failed\n").into_response();
+            }
+            Some(AttachmentInfo {
+                content_type: upload.content_type.to_string(),
+                filename: upload.filename,
+                size: upload.bytes.len() as i64,
+            })
+        }
+        None => None,
+    };
+
+    let kind = match (&url, &note, &envelope) {
+        (_, _, Some(_)) => "envelope",
+        (Some(_), Some(_), None) => "url_note",
+        (Some(_), None, None) => "url",
+        (None, Some(_), None) => "note",
+        (None, None, None) => "file",
+    };
+    // Free-form signets would mint a series each; they count as "other".
+    let known = state.signets.knows(&emoji) || registry::lookup(&emoji).is_some();
+    let signet_label = if known { emoji.as_str() } else { "other" };
+    state.metrics.inc(
+        "amigo_dispatches_total",
+        &[("kind", kind), ("signet", signet_label)],
+    );
+
+    let key = LinkKey { ns, slug };
+    let short_link = format!(
+        "{}/{}",
+        state.config.server.base_url.trim_end_matches('/'),
+        key.path()
+    );
+    let link_hook = webhook_url.as_deref().zip(webhook_secret.as_deref());
+    let created = serde_json::json!({
+        "event": "link.created",
+        "link": key.path(),
+        "short": short_link,
+        "ts": created_at,
+        "expires_at": expires_at,
+        "max_views": max_views,
+        "not_before": not_before,
+    });
+    state
+        .webhooks
+        .enqueue(&state.pool, link_hook, "link.created", &created, created_at)
+        .await;
 
     // CLI-friendly response if Accept: text/plain OR UA includes curl/wget/httpie
     let accept = headers
@@ -434,6 +1131,19 @@ async fn dispatch_link(
             header::CONTENT_TYPE,
             HeaderValue::from_static("text/plain; charset=utf-8"),
         );
+        // Keep stdout a bare link for pipes; the owner token rides in a header.
+        if let Ok(value) = HeaderValue::from_str(&owner_token) {
+            h.insert(OWNER_TOKEN_HEADER, value);
+        }
+        if let Ok(value) = HeaderValue::from_str(&receipt_id) {
+            h.insert(RECEIPT_HEADER, value);
+        }
+        if let Some(value) = webhook_secret
+            .as_deref()
+            .and_then(|secret| HeaderValue::from_str(secret).ok())
+        {
+            h.insert(WEBHOOK_SECRET_HEADER, value);
+        }
         return (StatusCode::OK, h, format!("{short_link}\n")).into_response();
     }
 
@@ -441,29 +1151,62 @@ async fn dispatch_link(
         short: short_link,
         original: url,
         note,
+        owner_token,
+        receipt_id,
+        webhook_secret,
+        stripped_params,
+        attachment,
     };
     (StatusCode::OK, Json(body)).into_response()
 }
 
 async fn resolve_slug(
     State(state): State<Arc<AppState>>,
-    Path(slug): Path<String>,
+    key: LinkKey,
+    Query(params): Query<HashMap<String, String>>,
     headers: HeaderMap,
 ) -> impl IntoResponse {
     let now = now_unix();
-    match db::get_link(&state.pool, &slug, now).await {
+    match state.store.get_with_pending(&key.ns, &key.slug, now).await {
         Ok(Some(row)) => {
-            let room = format!(
-                "{}/r/{}",
-                state.web_base_url.trim_end_matches('/'),
-                slug
-            );
+            state.metrics.inc("amigo_resolves_total", &[]);
+            let web = state.config.server.web_base_url.trim_end_matches('/');
+            let room = if key.ns.is_empty() {
+                format!("{web}/r/{}", key.slug)
+            } else {
+                format!("{web}/{}", key.path())
+            };
+
+            // Blocked: the Room explains, CLI gets 403. Never the destination.
+            if row.blocked.is_some() {
+                if is_cli_request(&headers) {
+                    return blocked_response(&headers);
+                }
+                return Redirect::temporary(&room).into_response();
+            }
+
+            // Not open yet: the Room shows the countdown, CLI gets 425.
+            if let Some(opens_at) = row.pending(now) {
+                if is_cli_request(&headers) {
+                    return not_yet(opens_at, now);
+                }
+                return Redirect::temporary(&room).into_response();
+            }
 
             if row.url.is_none() {
                 return Redirect::temporary(&room).into_response();
             }
 
             if is_cli_request(&headers) {
+                // Locked links only redirect with proof; the Room prompts browsers.
+                if let Some(phc) = row.password_hash.as_deref() {
+                    let unlock = params.get("unlock").map(String::as_str);
+                    if let Err(denied) =
+                        check_unlock(&state, &key.path(), phc, &headers, unlock).await
+                    {
+                        return denied;
+                    }
+                }
                 if let Some(url) = row.url.as_deref() {
                     return Redirect::temporary(url).into_response();
                 }
@@ -477,19 +1220,45 @@ async fn resolve_slug(
 
 async fn resolve_json(
     State(state): State<Arc<AppState>>,
-    Path(slug): Path<String>,
+    key: LinkKey,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
     let now = now_unix();
-    match db::get_link(&state.pool, &slug, now).await {
+    match state.store.get_with_pending(&key.ns, &key.slug, now).await {
         Ok(Some(row)) => {
-            let db::LinkRow {
+            if row.blocked.is_some() {
+                return blocked_response(&headers);
+            }
+            if let Some(opens_at) = row.pending(now) {
+                return (StatusCode::TOO_EARLY, Json(NotYetResponse { opens_at })).into_response();
+            }
+            let store::LinkRow {
                 url,
                 note,
                 expires_at,
                 reply_to,
                 emoji,
+                envelope,
+                password_hash,
+                ..
             } = row;
-            let emoji = Some(resolve_signet(url.as_deref(), emoji));
+            if let Some(phc) = password_hash.as_deref() {
+                let unlock = params.get("unlock").map(String::as_str);
+                if let Err(denied) = check_unlock(&state, &key.path(), phc, &headers, unlock).await
+                {
+                    return denied;
+                }
+            }
+            let emoji = Some(state.signets.resolve(url.as_deref(), emoji));
+            let envelope = envelope.and_then(|raw| serde_json::from_str(&raw).ok());
+            let attachment = match db::attachment_meta(&state.pool, &key.ns, &key.slug).await {
+                Ok(meta) => meta.map(AttachmentInfo::from),
+                Err(_) => {
+                    return (StatusCode::INTERNAL_SERVER_ERROR, "resolve failed\n").into_response()
+                }
+            };
+            state.metrics.inc("amigo_resolves_total", &[]);
             (
                 StatusCode::OK,
                 Json(ResolveResponse {
@@ -498,6 +1267,8 @@ async fn resolve_json(
                     expires_at,
                     reply_to,
                     emoji,
+                    envelope,
+                    attachment,
                 }),
             )
                 .into_response()
@@ -506,16 +1277,21 @@ async fn resolve_json(
     }
 }
 
-async fn peek_link(
-    State(state): State<Arc<AppState>>,
-    Path(slug): Path<String>,
-) -> impl IntoResponse {
+async fn peek_link(State(state): State<Arc<AppState>>, key: LinkKey) -> impl IntoResponse {
     let now = now_unix();
-    match db::get_link(&state.pool, &slug, now).await {
+    match state.store.get_with_pending(&key.ns, &key.slug, now).await {
         Ok(Some(row)) => {
-            let db::LinkRow { url, emoji, .. } = row;
+            let opens_at = row.pending(now);
+            let store::LinkRow {
+                url,
+                emoji,
+                password_hash,
+                blocked,
+                ..
+            } = row;
             let has_url = url.is_some();
-            let emoji = Some(resolve_signet(url.as_deref(), emoji));
+            let locked = password_hash.is_some();
+            let emoji = Some(state.signets.resolve(url.as_deref(), emoji));
             (
                 StatusCode::OK,
                 Json(PeekResponse {
@@ -523,6 +1299,9 @@ async fn peek_link(
                     gone: false,
                     emoji,
                     has_url,
+                    locked,
+                    opens_at,
+                    blocked: blocked.is_some(),
                 }),
             )
                 .into_response()
@@ -534,6 +1313,9 @@ async fn peek_link(
                 gone: true,
                 emoji: None,
                 has_url: false,
+                locked: false,
+                opens_at: None,
+                blocked: false,
             }),
         )
             .into_response(),
@@ -541,6 +1323,329 @@ async fn peek_link(
     }
 }
 
+// The file behind a commence grant (x-attachment-token or ?token=). One
+// download per grant; after the last view it is the file's last, too.
+async fn attachment_handler(
+    State(state): State<Arc<AppState>>,
+    key: LinkKey,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Response {
+    let grant = headers
+        .get(ATTACHMENT_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .or_else(|| params.get("token").map(String::as_str))
+        .map(str::trim)
+        .filter(|v| !v.is_empty());
+    let Some(grant) = grant else {
+        return (StatusCode::UNAUTHORIZED, "commence first\n").into_response();
+    };
+    let file = match state
+        .attachments
+        .take(&state.pool, &key.path(), grant)
+        .await
+    {
+        Ok(Some(file)) => file,
+        Ok(None) => return (StatusCode::NOT_FOUND, "Urma s-a sters.\n").into_response(),
+        Err(err) => {
+            tracing::error!(error = ?err, "attachment read failed");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "attachment failed\n").into_response();
+        }
+    };
+    let mut h = HeaderMap::new();
+    if let Ok(value) = HeaderValue::from_str(&file.content_type) {
+        h.insert(header::CONTENT_TYPE, value);
+    }
+    let disposition = match file.filename.as_deref() {
+        Some(name) => format!(
+            "inline; filename=\"{}\"",
+            name.replace(|c: char| !c.is_ascii(), "_")
+        ),
+        None => "inline".to_string(),
+    };
+    if let Ok(value) = HeaderValue::from_str(&disposition) {
+        h.insert(header::CONTENT_DISPOSITION, value);
+    }
+    h.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    h.insert(
+        header::X_CONTENT_TYPE_OPTIONS,
+        HeaderValue::from_static("nosniff"),
+    );
+    (StatusCode::OK, h, file.bytes).into_response()
+}
+
+#[derive(Serialize)]
+struct SignetList {
+    mode: registry::SignetMode,
+    signets: &'static [registry::Signet],
+}
+
+// The Protocol Set. CLI clients get one line per signet in ?lang= (en).
+async fn signets_list(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Response {
+    if is_cli_request(&headers) {
+        let lang = params.get("lang").map(|v| v.trim().to_ascii_lowercase());
+        let mut body = String::new();
+        for signet in registry::PROTOCOL_SET {
+            let text = match lang.as_deref() {
+                Some("ro") => signet.descriptions.ro,
+                Some("es") => signet.descriptions.es,
+                _ => signet.descriptions.en,
+            };
+            body.push_str(&format!(
+                "{} {} ({}) - {}\n",
+                signet.glyph, signet.name, signet.key, text
+            ));
+        }
+        return (StatusCode::OK, body).into_response();
+    }
+    let list = SignetList {
+        mode: state.signet_policy.mode,
+        signets: registry::PROTOCOL_SET,
+    };
+    (StatusCode::OK, Json(list)).into_response()
+}
+
+#[derive(Serialize)]
+struct SignetPreview {
+    #[serde(flatten)]
+    detection: signet::Detection,
+    // The url as dispatch would store it (normalized, tracking stripped).
+    url: String,
+}
+
+// What dispatch would pick for this url, before anything is created.
+async fn signet_detect(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Response {
+    let Some(raw) = clean_opt(params.get("url").cloned()) else {
+        return (StatusCode::BAD_REQUEST, "missing url\n").into_response();
+    };
+    let url = match state.url_policy.normalize(&raw) {
+        Ok(url) => url,
+        Err(err) => return bad_url("url", err, &headers),
+    };
+    let strip = match params.get("strip_tracking").map(|v| v.trim()) {
+        Some("0" | "false" | "no" | "off") => false,
+        Some(_) => true,
+        None => state.tracking.enabled(),
+    };
+    let url = if strip {
+        state.tracking.strip(&url).0
+    } else {
+        url
+    };
+    let detection = state.signets.detect(&url);
+    if is_cli_request(&headers) {
+        return (StatusCode::OK, format!("{}\n", detection.signet)).into_response();
+    }
+    (StatusCode::OK, Json(SignetPreview { detection, url })).into_response()
+}
+
+// QR of the short link, SVG or PNG. Peek-style lookup: printing a code
+// never spends a view.
+async fn qr_handler(
+    State(state): State<Arc<AppState>>,
+    key: LinkKey,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Response {
+    let now = now_unix();
+    let row = match state.store.get_with_pending(&key.ns, &key.slug, now).await {
+        Ok(Some(row)) => row,
+        Ok(None) => return (StatusCode::NOT_FOUND, "Urma s-a sters.\n").into_response(),
+        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "qr failed\n").into_response(),
+    };
+    if row.blocked.is_some() {
+        return blocked_response(&headers);
+    }
+
+    let format = match params.get("format").map(|v| v.trim().to_ascii_lowercase()) {
+        Some(v) if v == "svg" => qr::Format::Svg,
+        Some(v) if v == "png" => qr::Format::Png,
+        Some(_) => return (StatusCode::BAD_REQUEST, "format must be svg or png\n").into_response(),
+        None => {
+            let accept = headers
+                .get(header::ACCEPT)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("");
+            if accept.contains("image/png") && !accept.contains("image/svg") {
+                qr::Format::Png
+            } else {
+                qr::Format::Svg
+            }
+        }
+    };
+
+    let size = match params.get("size").map(|v| v.trim().parse::<u32>()) {
+        None => qr::DEFAULT_SIZE,
+        Some(Ok(size)) if (qr::MIN_SIZE..=qr::MAX_SIZE).contains(&size) => size,
+        Some(_) => {
+            let msg = format!("size must be {}-{}\n", qr::MIN_SIZE, qr::MAX_SIZE);
+            return (StatusCode::BAD_REQUEST, msg).into_response();
+        }
+    };
+    let margin = match params.get("margin").map(|v| v.trim().parse::<u32>()) {
+        None => qr::DEFAULT_MARGIN,
+        Some(Ok(margin)) if margin <= qr::MAX_MARGIN => margin,
+        Some(_) => {
+            let msg = format!("margin must be 0-{}\n", qr::MAX_MARGIN);
+            return (StatusCode::BAD_REQUEST, msg).into_response();
+        }
+    };
+    let with_signet = match params.get("signet").map(|v| config::flag(v)) {
+        None => false,
+        Some(Ok(on)) => on,
+        Some(Err(_)) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                "signet must be true or false
+",
+            )
+                .into_response()
+        }
+    };
+    if with_signet && format == qr::Format::Png {
+        return (StatusCode::BAD_REQUEST, "signet needs format=svg\n").into_response();
+    }
+    // The signet hides the centre modules; only Q / H can rebuild them.
+    let ecc = match params.get("ecc").map(|v| qr::parse_ecc(v)) {
+        None if with_signet => qrcode::EcLevel::H,
+        None => qrcode::EcLevel::M,
+        Some(Some(qrcode::EcLevel::L | qrcode::EcLevel::M)) if with_signet => {
+            return (StatusCode::BAD_REQUEST, "signet needs ecc=Q or H\n").into_response();
+        }
+        Some(Some(ecc)) => ecc,
+        Some(None) => {
+            return (StatusCode::BAD_REQUEST, "ecc must be L, M, Q or H\n").into_response()
+        }
+    };
+
+    let opts = qr::QrOptions {
+        size,
+        margin,
+        ecc,
+        signet: with_signet.then(|| state.signets.resolve(row.url.as_deref(), row.emoji)),
+    };
+    let short_link = format!(
+        "{}/{}",
+        state.config.server.base_url.trim_end_matches('/'),
+        key.path()
+    );
+    let rendered = match format {
+        qr::Format::Svg => {
+            qr::render_svg(&short_link, &opts).map(|svg| ("image/svg+xml", svg.into_bytes()))
+        }
+        qr::Format::Png => qr::render_png(&short_link, &opts).map(|png| ("image/png", png)),
+    };
+    match rendered {
+        Ok((content_type, body)) => (
+            [
+                (header::CONTENT_TYPE, content_type),
+                (header::CACHE_CONTROL, "private, max-age=300"),
+            ],
+            body,
+        )
+            .into_response(),
+        Err(err) => {
+            tracing::error!(error = %err, "qr render failed");
+            (StatusCode::INTERNAL_SERVER_ERROR, "qr failed\n").into_response()
+        }
+    }
+}
+
+fn thread_item(row: store::ThreadRow) -> ThreadItem {
+    let emoji = Some(row.emoji.unwrap_or_else(|| DEFAULT_SIGNET.to_string()));
+    ThreadItem {
+        path: LinkKey {
+            ns: row.callsign,
+            slug: row.slug,
+        }
+        .path(),
+        reply_to: row.reply_to,
+        emoji,
+        has_url: row.has_url,
+        locked: row.locked,
+        created_at: row.created_at,
+    }
+}
+
+// Reply chain around a link: climb to the oldest live ancestor, then walk
+// every live reply below it. Peek-level data only; no views are spent.
+async fn thread_handler(State(state): State<Arc<AppState>>, key: LinkKey) -> impl IntoResponse {
+    let now = now_unix();
+    let mut root = match state.store.thread_node(&key.ns, &key.slug, now).await {
+        Ok(Some(row)) => row,
+        Ok(None) => return (StatusCode::NOT_FOUND, "not-found\n").into_response(),
+        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "thread failed\n").into_response(),
+    };
+
+    let mut seen = std::collections::HashSet::new();
+    seen.insert(key.path());
+    for _ in 0..MAX_THREAD_DEPTH {
+        let Some(parent) = root.reply_to.as_deref().and_then(LinkKey::parse) else {
+            break;
+        };
+        if !seen.insert(parent.path()) {
+            break;
+        }
+        match state.store.thread_node(&parent.ns, &parent.slug, now).await {
+            Ok(Some(row)) => root = row,
+            Ok(None) => break,
+            Err(_) => {
+                return (StatusCode::INTERNAL_SERVER_ERROR, "thread failed\n").into_response()
+            }
+        }
+    }
+
+    let root = thread_item(root);
+    let root_path = root.path.clone();
+    let mut seen = std::collections::HashSet::from([root_path.clone()]);
+    let mut queue = std::collections::VecDeque::from([root_path.clone()]);
+    let mut items = vec![root];
+    while let Some(parent) = queue.pop_front() {
+        let replies = match state.store.thread_replies(&parent, now).await {
+            Ok(rows) => rows,
+            Err(_) => {
+                return (StatusCode::INTERNAL_SERVER_ERROR, "thread failed\n").into_response()
+            }
+        };
+        for row in replies {
+            if items.len() >= MAX_THREAD_ITEMS {
+                break;
+            }
+            let item = thread_item(row);
+            if seen.insert(item.path.clone()) {
+                queue.push_back(item.path.clone());
+                items.push(item);
+            }
+        }
+    }
+    items.sort_by_key(|item| item.created_at);
+
+    Json(ThreadResponse {
+        root: root_path,
+        items,
+    })
+    .into_response()
+}
+
+async fn metrics_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let rooms = state.joint.rooms.read().await.len();
+    (
+        [(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("text/plain; version=0.0.4"),
+        )],
+        state.metrics.render(rooms),
+    )
+}
+
 async fn health() -> impl IntoResponse {
     (StatusCode::OK, "ok\n")
 }
@@ -548,38 +1653,398 @@ async fn health() -> impl IntoResponse {
 // Proof of Breath: User commits to journey (increment views)
 async fn commence_handler(
     State(state): State<Arc<AppState>>,
-    Path(slug): Path<String>,
+    key: LinkKey,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
     let now = now_unix();
-    match db::commence_journey(&state.pool, &slug, now).await {
-        Ok(true) => StatusCode::OK,
-        Ok(false) => StatusCode::NOT_FOUND, // Link doesn't exist or expired
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    // Locked links: no proof, no view spent. Scheduled ones: not yet.
+    match state.store.get_with_pending(&key.ns, &key.slug, now).await {
+        Ok(Some(row)) => {
+            if row.blocked.is_some() {
+                return blocked_response(&headers);
+            }
+            if let Some(opens_at) = row.pending(now) {
+                return not_yet(opens_at, now);
+            }
+            if let Some(phc) = row.password_hash.as_deref() {
+                let unlock = params.get("unlock").map(String::as_str);
+                if let Err(denied) = check_unlock(&state, &key.path(), phc, &headers, unlock).await
+                {
+                    return denied;
+                }
+            }
+        }
+        Ok(None) => return StatusCode::NOT_FOUND.into_response(),
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+    match state.store.commence(&key.ns, &key.slug, now).await {
+        Ok(Some(journey)) => {
+            state.metrics.inc("amigo_commences_total", &[]);
+            let opened = ReceiptKind::Opened {
+                views: journey.views,
+                remaining: journey.max_views.map(|limit| limit - journey.views),
+            };
+            announce(&state, &journey.watchers, opened, now).await;
+            if journey.consumed {
+                state
+                    .attachments
+                    .release(&state.pool, &key.ns, &key.slug, now)
+                    .await;
+                announce(&state, &journey.watchers, ReceiptKind::Burned, now).await;
+            }
+            let path = key.path();
+            match state
+                .attachments
+                .grant(&state.pool, &key.ns, &key.slug, &path)
+                .await
+            {
+                Ok(Some((token, meta))) => {
+                    let url = format!(
+                        "{}/api/attachment/{path}",
+                        state.config.server.base_url.trim_end_matches('/')
+                    );
+                    let attachment = AttachmentGrant {
+                        token,
+                        url,
+                        expires_in: attachment::GRANT_TTL.as_secs(),
+                        info: meta.into(),
+                    };
+                    Json(CommenceResponse { attachment }).into_response()
+                }
+                Ok(None) => StatusCode::OK.into_response(),
+                Err(err) => {
+                    tracing::error!(error = ?err, "attachment grant failed");
+                    StatusCode::OK.into_response()
+                }
+            }
+        }
+        Ok(None) => StatusCode::NOT_FOUND.into_response(), // Link doesn't exist or expired
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
     }
 }
 
-// Absolute burn: hard delete regardless of type.
+// Trade the password for a short-lived unlock token (Room prompt / CLI).
+async fn unlock_handler(
+    State(state): State<Arc<AppState>>,
+    key: LinkKey,
+    Json(payload): Json<UnlockPayload>,
+) -> impl IntoResponse {
+    let now = now_unix();
+    let phc = match state.store.get(&key.ns, &key.slug, now).await {
+        Ok(Some(row)) => match row.password_hash {
+            Some(phc) => phc,
+            None => return (StatusCode::BAD_REQUEST, "not locked\n").into_response(),
+        },
+        Ok(None) => return (StatusCode::NOT_FOUND, "not-found\n").into_response(),
+        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "unlock failed\n").into_response(),
+    };
+
+    if let Err(denied) = verify_or_throttle(&state, &key.path(), &phc, payload.password).await {
+        return denied;
+    }
+
+    let unlock_token = state.locks.issue(&key.path()).await;
+    Json(UnlockResponse {
+        unlock_token,
+        expires_in: lock::UNLOCK_TTL.as_secs(),
+    })
+    .into_response()
+}
+
+// Absolute burn: hard delete regardless of type. Owner only.
 async fn burn_handler(
     State(state): State<Arc<AppState>>,
-    Path(slug): Path<String>,
+    key: LinkKey,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
-    match db::burn_link(&state.pool, &slug).await {
-        Ok(_) => (StatusCode::OK, "ok\n").into_response(),
+    if let Err(denied) = authorize_owner(&state, &key, &headers).await {
+        return denied;
+    }
+    match state.store.burn(&key.ns, &key.slug).await {
+        Ok(gone) => {
+            if let Some(watchers) = gone {
+                state.metrics.inc("amigo_burns_total", &[]);
+                state
+                    .attachments
+                    .remove(&state.pool, &key.ns, &key.slug)
+                    .await;
+                announce(&state, &watchers, ReceiptKind::Burned, now_unix()).await;
+            }
+            (StatusCode::OK, "ok\n").into_response()
+        }
         Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "burn failed\n").into_response(),
     }
 }
 
+// Owner edit: fix a typo without burning and re-sending. Views stay put.
+async fn link_patch(
+    State(state): State<Arc<AppState>>,
+    key: LinkKey,
+    headers: HeaderMap,
+    Json(patch): Json<LinkPatch>,
+) -> impl IntoResponse {
+    if let Err(denied) = authorize_owner(&state, &key, &headers).await {
+        return denied;
+    }
+
+    let now = now_unix();
+    let row = match state.store.get_with_pending(&key.ns, &key.slug, now).await {
+        Ok(Some(row)) => row,
+        Ok(None) => return (StatusCode::GONE, "already consumed or expired\n").into_response(),
+        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "edit failed\n").into_response(),
+    };
+
+    let url = match patch.url.map(|value| value.trim().to_string()) {
+        Some(value) if value.is_empty() => {
+            return (StatusCode::BAD_REQUEST, "url cannot be removed\n").into_response();
+        }
+        Some(_) if row.url.is_none() => {
+            return (StatusCode::BAD_REQUEST, "note-only link has no url\n").into_response();
+        }
+        Some(value) => match state.url_policy.normalize(&value) {
+            Ok(value) if state.denylist.check(&value).is_some() => {
+                return bad_url("url", blocked_url(), &headers);
+            }
+            Ok(value) => Some(value),
+            Err(err) => return bad_url("url", err, &headers),
+        },
+        None => row.url.clone(),
+    };
+
+    let note = match patch.note {
+        Some(value) => clean_opt(Some(value)),
+        None => row.note.clone(),
+    };
+    if note.is_some() && row.envelope.is_some() {
+        return (StatusCode::BAD_REQUEST, "note and envelope are exclusive\n").into_response();
+    }
+    let attachment = match db::attachment_meta(&state.pool, &key.ns, &key.slug).await {
+        Ok(meta) => meta.map(AttachmentInfo::from),
+        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "edit failed\n").into_response(),
+    };
+    if url.is_none() && note.is_none() && row.envelope.is_none() && attachment.is_none() {
+        return (StatusCode::BAD_REQUEST, "missing url or note\n").into_response();
+    }
+
+    // A detected signet follows the url; a hand-picked one stays.
+    let emoji = match patch.emoji {
+        Some(value) => match state.signet_policy.check(Some(value)) {
+            Ok(emoji) => state.signets.resolve(url.as_deref(), emoji),
+            Err(msg) => return (StatusCode::BAD_REQUEST, msg).into_response(),
+        },
+        None if url != row.url => {
+            let detected = row
+                .url
+                .as_deref()
+                .map(|url| state.signets.detect(url).signet);
+            let kept = row
+                .emoji
+                .clone()
+                .filter(|value| Some(value) != detected.as_ref());
+            state.signets.resolve(url.as_deref(), kept)
+        }
+        None => state.signets.resolve(url.as_deref(), row.emoji.clone()),
+    };
+
+    let expires_at = if patch.ttl.is_some() || patch.expires_at.is_some() {
+        let start = row.pending(now).unwrap_or(now);
+        match resolve_expiry(patch.ttl, patch.expires_at, start, now, &state.config.links) {
+            Ok(ts) => Some(ts),
+            Err(msg) => return (StatusCode::BAD_REQUEST, msg).into_response(),
+        }
+    } else {
+        row.expires_at
+    };
+
+    let max_views = match patch.max_views {
+        Some(_) if row.url.is_none() => {
+            return (StatusCode::BAD_REQUEST, "note-only links are single view\n").into_response();
+        }
+        Some(limit) if limit <= 0 => None,
+        Some(limit) if limit <= row.views => {
+            return (StatusCode::BAD_REQUEST, "max_views below current views\n").into_response();
+        }
+        Some(limit) => Some(limit),
+        None => row.max_views,
+    };
+
+    // Re-checked on every edit, so a listed link stays blocked.
+    let blocked = url.as_deref().and_then(|url| state.denylist.check(url));
+    let update = store::LinkUpdate {
+        blocked: blocked.as_deref(),
+        url: url.as_deref(),
+        note: note.as_deref(),
+        emoji: Some(emoji.as_str()),
+        expires_at,
+        max_views,
+    };
+    match state.store.update(&key.ns, &key.slug, &update, now).await {
+        Ok(true) => {}
+        Ok(false) => return (StatusCode::GONE, "already consumed or expired\n").into_response(),
+        Err(err) => {
+            tracing::error!(error = ?err, "failed to update link");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "edit failed\n").into_response();
+        }
+    }
+
+    let envelope = row.envelope.and_then(|raw| serde_json::from_str(&raw).ok());
+    Json(ResolveResponse {
+        url,
+        note,
+        expires_at,
+        reply_to: row.reply_to,
+        emoji: Some(emoji),
+        envelope,
+        attachment,
+    })
+    .into_response()
+}
+
+#[derive(Serialize)]
+struct DayOpens {
+    day: String,
+    opens: i64,
+}
+
+#[derive(Serialize)]
+struct LinkStats {
+    // live, pending (not open yet), expired (awaiting the sweeper), consumed
+    state: &'static str,
+    views: i64,
+    max_views: Option<i64>,
+    remaining: Option<i64>,
+    created_at: i64,
+    expires_at: Option<i64>,
+    first_open_at: Option<i64>,
+    last_open_at: Option<i64>,
+    consumed_at: Option<i64>,
+    // UTC days with at least one open.
+    opens_per_day: Vec<DayOpens>,
+}
+
+// Owner view of how a link did. Works after the last view too: consumed
+// links leave a tombstone until they would have expired.
+async fn link_stats(
+    State(state): State<Arc<AppState>>,
+    key: LinkKey,
+    headers: HeaderMap,
+) -> Response {
+    let Some(presented) = token::bearer(&headers) else {
+        return (StatusCode::UNAUTHORIZED, "owner token required\n").into_response();
+    };
+    let row = match state.store.stats_row(&key.ns, &key.slug).await {
+        Ok(Some(row)) => row,
+        Ok(None) => return (StatusCode::NOT_FOUND, "not-found\n").into_response(),
+        Err(err) => {
+            tracing::error!(error = ?err, "stats lookup failed");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "stats failed\n").into_response();
+        }
+    };
+    if !row
+        .owner_hash
+        .as_deref()
+        .is_some_and(|hash| token::verify(presented, hash))
+    {
+        return (StatusCode::FORBIDDEN, "forbidden\n").into_response();
+    }
+    let opens = match state.store.open_stats(&key.ns, &key.slug).await {
+        Ok(opens) => opens,
+        Err(err) => {
+            tracing::error!(error = ?err, "stats lookup failed");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "stats failed\n").into_response();
+        }
+    };
+
+    let now = now_unix();
+    let link_state = if row.consumed_at.is_some() {
+        "consumed"
+    } else if row.expires_at.is_some_and(|ts| ts <= now) {
+        "expired"
+    } else if row.not_before.is_some_and(|ts| ts > now) {
+        "pending"
+    } else {
+        "live"
+    };
+    let opens_per_day = opens
+        .per_day
+        .into_iter()
+        .filter_map(|(day, count)| {
+            let date = OffsetDateTime::from_unix_timestamp(day * 86400)
+                .ok()?
+                .date();
+            Some(DayOpens {
+                day: date.to_string(),
+                opens: count,
+            })
+        })
+        .collect();
+    Json(LinkStats {
+        state: link_state,
+        views: row.views,
+        max_views: row.max_views,
+        remaining: row.max_views.map(|limit| (limit - row.views).max(0)),
+        created_at: row.created_at,
+        expires_at: row.expires_at,
+        first_open_at: opens.first,
+        last_open_at: opens.last,
+        consumed_at: row.consumed_at,
+        opens_per_day,
+    })
+    .into_response()
+}
+
+async fn callsign_claim(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<CallsignPayload>,
+) -> impl IntoResponse {
+    let name = match callsign::check_claim(&payload.name) {
+        Ok(name) => name,
+        Err(msg) => return (StatusCode::BAD_REQUEST, msg).into_response(),
+    };
+
+    let owner_key = token::gen_token();
+    let owner_hash = token::hash_token(&owner_key);
+    match state
+        .store
+        .claim_callsign(&name, &owner_hash, now_unix())
+        .await
+    {
+        Ok(()) => Json(CallsignResponse {
+            callsign: format!("@{name}"),
+            owner_key,
+        })
+        .into_response(),
+        Err(err) if store::is_conflict(&err) => {
+            (StatusCode::CONFLICT, "callsign taken\n").into_response()
+        }
+        Err(err) => {
+            tracing::error!(error = ?err, "failed to claim callsign");
+            (StatusCode::INTERNAL_SERVER_ERROR, "claim failed\n").into_response()
+        }
+    }
+}
+
 async fn joint_create(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    let mut id = slug::gen_slug(6);
+    let slug_length = state.config.links.slug_length;
+    let mut id = slug::gen_slug(slug_length);
     for _ in 0..5 {
         if state.joint.get_room(&id).await.is_none() {
             break;
         }
-        id = slug::gen_slug(6);
+        id = slug::gen_slug(slug_length);
     }
-    state.joint.create_room(id.clone()).await;
-    let url = format!("{}/joint/{}", state.web_base_url, id);
-    Json(JointCreateResponse { id, url })
+    let owner_token = token::gen_token();
+    state
+        .joint
+        .create_room(id.clone(), token::hash_token(&owner_token))
+        .await;
+    let url = format!("{}/joint/{}", state.config.server.web_base_url, id);
+    Json(JointCreateResponse {
+        id,
+        url,
+        owner_token,
+    })
 }
 
 async fn joint_status(
@@ -605,7 +2070,17 @@ async fn joint_status(
 async fn joint_burn(
     State(state): State<Arc<AppState>>,
     Path(id): Path<String>,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
+    let Some(room) = state.joint.get_room(&id).await else {
+        return (StatusCode::NOT_FOUND, "not found\n").into_response();
+    };
+    let Some(presented) = token::bearer(&headers) else {
+        return (StatusCode::UNAUTHORIZED, "owner token required\n").into_response();
+    };
+    if !token::verify(presented, &room.owner_hash) {
+        return (StatusCode::FORBIDDEN, "forbidden\n").into_response();
+    }
     if state.joint.burn_room(&id).await {
         (StatusCode::OK, "ok\n").into_response()
     } else {
@@ -631,10 +2106,17 @@ async fn joint_ws_handler(
         .filter(|v| !v.is_empty())
         .unwrap_or_else(|| "Guest".to_string());
 
-    ws.on_upgrade(move |socket| joint_ws(socket, room, name))
+    let metrics = state.metrics.clone();
+    ws.on_upgrade(move |socket| joint_ws(socket, room, name, metrics))
 }
 
-async fn joint_ws(socket: WebSocket, room: Arc<JointRoom>, name: String) {
+async fn joint_ws(
+    socket: WebSocket,
+    room: Arc<JointRoom>,
+    name: String,
+    metrics: Arc<metrics::Metrics>,
+) {
+    let _open = metrics.ws_open();
     if let Ok(mut last) = room.last_activity.lock() {
         *last = Instant::now();
     }
@@ -647,7 +2129,16 @@ async fn joint_ws(socket: WebSocket, room: Arc<JointRoom>, name: String) {
     let mut rx = room.tx.subscribe();
 
     let send_task = tokio::spawn(async move {
-        while let Ok(event) = rx.recv().await {
+        loop {
+            let event = match rx.recv().await {
+                Ok(event) => event,
+                // A slow reader misses some chat; it stays in the room.
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    metrics.add("amigo_broadcast_lagged_total", &[("channel", "joint")], n);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
             let Ok(payload) = serde_json::to_string(&event) else {
                 continue;
             };
@@ -701,6 +2192,229 @@ async fn joint_ws(socket: WebSocket, room: Arc<JointRoom>, name: String) {
     send_task.abort();
 }
 
+// Lifecycle fan-out: the sender's receipt stream and the webhooks (the
+// link's own plus the server-wide ones). Same event, two transports.
+async fn announce(state: &AppState, watchers: &store::Watchers, kind: ReceiptKind, now: i64) {
+    let key = LinkKey {
+        ns: watchers.callsign.clone(),
+        slug: watchers.slug.clone(),
+    };
+    let event = format!("link.{}", kind.name());
+    let mut payload = serde_json::json!({
+        "event": event,
+        "link": key.path(),
+        "short": format!("{}/{}", state.config.server.base_url.trim_end_matches('/'), key.path()),
+        "ts": now,
+    });
+    if let ReceiptKind::Opened { views, remaining } = &kind {
+        payload["views"] = (*views).into();
+        payload["remaining"] = (*remaining).into();
+    }
+    let link_hook = watchers
+        .webhook_url
+        .as_deref()
+        .zip(watchers.webhook_secret.as_deref());
+    state
+        .webhooks
+        .enqueue(&state.pool, link_hook, &event, &payload, now)
+        .await;
+
+    if let Some(hash) = watchers.receipt_hash.as_deref() {
+        state.receipts.emit(hash, kind, now).await;
+    }
+}
+
+// Read receipts for the sender: `Accept: text/event-stream` gets a live
+// stream (resumable via Last-Event-ID), anything else a long-poll that
+// returns as soon as there is news (`?after=<seq>&wait=<secs>`).
+async fn receipt_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Response {
+    let hash = token::hash_token(id.trim());
+    if !state.receipts.known(&hash).await {
+        match state.store.receipt_exists(&hash).await {
+            Ok(true) => {}
+            Ok(false) => return (StatusCode::NOT_FOUND, "not-found\n").into_response(),
+            Err(_) => {
+                return (StatusCode::INTERNAL_SERVER_ERROR, "receipt lookup failed\n")
+                    .into_response();
+            }
+        }
+    }
+
+    let wants_stream = headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/event-stream"));
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok());
+    let after = last_event_id
+        .or_else(|| params.get("after").and_then(|v| v.parse().ok()))
+        .unwrap_or(0);
+
+    let sub = state.receipts.subscribe(&hash, after).await;
+    if wants_stream {
+        return receipt_stream(sub, state.metrics.clone()).into_response();
+    }
+
+    let wait = params
+        .get("wait")
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(RECEIPT_WAIT_SECS)
+        .min(RECEIPT_MAX_WAIT_SECS);
+    Json(receipt_poll(sub, Duration::from_secs(wait), &state.metrics).await).into_response()
+}
+
+fn receipt_stream(
+    sub: Subscription,
+    metrics: Arc<metrics::Metrics>,
+) -> Sse<impl futures_util::Stream<Item = Result<Event, axum::Error>>> {
+    let done = sub.finished || sub.backlog.iter().any(|event| event.kind.is_final());
+    let live = futures_util::stream::unfold((sub.rx, done), move |(mut rx, done)| {
+        let metrics = metrics.clone();
+        async move {
+            if done {
+                return None;
+            }
+            loop {
+                match rx.recv().await {
+                    Ok(event) => {
+                        let done = event.kind.is_final();
+                        return Some((event, (rx, done)));
+                    }
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        metrics.add(
+                            "amigo_broadcast_lagged_total",
+                            &[("channel", "receipts")],
+                            n,
+                        );
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        }
+    });
+    let events = futures_util::stream::iter(sub.backlog)
+        .chain(live)
+        .map(|event| {
+            Event::default()
+                .event(event.kind.name())
+                .id(event.seq.to_string())
+                .json_data(&event)
+        });
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+async fn receipt_poll(
+    mut sub: Subscription,
+    wait: Duration,
+    metrics: &metrics::Metrics,
+) -> ReceiptPoll {
+    if !sub.backlog.is_empty() || sub.finished {
+        let finished = sub.finished;
+        return ReceiptPoll {
+            events: sub.backlog,
+            finished,
+        };
+    }
+    let deadline = tokio::time::Instant::now() + wait;
+    loop {
+        match tokio::time::timeout_at(deadline, sub.rx.recv()).await {
+            Ok(Ok(event)) => {
+                let finished = event.kind.is_final();
+                return ReceiptPoll {
+                    events: vec![event],
+                    finished,
+                };
+            }
+            Ok(Err(broadcast::error::RecvError::Lagged(n))) => {
+                metrics.add(
+                    "amigo_broadcast_lagged_total",
+                    &[("channel", "receipts")],
+                    n,
+                );
+                continue;
+            }
+            _ => {
+                return ReceiptPoll {
+                    events: Vec::new(),
+                    finished: false,
+                };
+            }
+        }
+    }
+}
+
+// What the sweeper does for each row it took: the file goes, the
+// sender hears about it.
+async fn expire_link(state: &AppState, watchers: &store::Watchers, now: i64) {
+    state
+        .attachments
+        .remove(&state.pool, &watchers.callsign, &watchers.slug)
+        .await;
+    announce(state, watchers, ReceiptKind::Expired, now).await;
+}
+
+// "Urma s-a sters" for real: expired rows leave the disk, not just the API.
+async fn sweep_expired(state: &AppState, cfg: &config::SweepConfig) {
+    let pool = &state.pool;
+    let now = now_unix();
+    let mut purged = 0u64;
+    loop {
+        match state.store.purge(now, cfg.batch_size).await {
+            Ok(gone) => {
+                let n = gone.len() as u64;
+                purged += n;
+                for watchers in &gone {
+                    expire_link(state, watchers, now).await;
+                }
+                if n < cfg.batch_size as u64 {
+                    break;
+                }
+                tokio::task::yield_now().await;
+            }
+            Err(err) => {
+                tracing::error!(error = ?err, "sweep failed");
+                break;
+            }
+        }
+    }
+
+    if let Err(err) = state.store.purge_stats(now).await {
+        tracing::error!(error = ?err, "stats sweep failed");
+    }
+
+    if purged == 0 {
+        return;
+    }
+    tracing::info!(purged, "swept expired links");
+
+    if cfg.vacuum {
+        if let Err(err) = db::vacuum(pool).await {
+            tracing::error!(error = ?err, "vacuum failed");
+        }
+    }
+}
+
+fn print_report(what: &str, report: &migrate::Report, dry_run: bool) {
+    let verb = if dry_run { "would apply" } else { "applied" };
+    println!(
+        "{what} version {} -> {} ({verb} {} migrations)",
+        report.from,
+        report.to,
+        report.applied.len()
+    );
+    for migration in &report.applied {
+        println!("  {:>3} {}", migration.version, migration.name);
+    }
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::registry()
@@ -708,49 +2422,212 @@ async fn main() -> anyhow::Result<()> {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    let database_url =
-        std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite:./data/amigo.db".to_string());
-    let base_url = std::env::var("BASE_URL").unwrap_or_else(|_| "http://localhost:3000".to_string());
-    let web_base_url = std::env::var("WEB_BASE_URL").unwrap_or_else(|_| "http://localhost:3001".to_string());
-
-    let pool = db::connect(&database_url).await?;
-    let joint = Arc::new(JointHub::new());
-    let state = Arc::new(AppState { pool, base_url, web_base_url, joint: joint.clone() });
+    // --config <path>, else AMIGO_CONFIG, else ./amigo.toml if present.
+    // --migrate-only: bring the schema up to date and exit (--dry-run: say
+    // what would run, change nothing).
+    let mut args = std::env::args().skip(1);
+    let mut config_path = std::env::var("AMIGO_CONFIG").ok().filter(|p| !p.is_empty());
+    let (mut migrate_only, mut dry_run) = (false, false);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--migrate-only" => migrate_only = true,
+            "--dry-run" => dry_run = true,
+            "--config" => {
+                config_path = Some(
+                    args.next()
+                        .ok_or_else(|| anyhow::anyhow!("--config needs a path"))?,
+                )
+            }
+            other => anyhow::bail!("unknown argument: {other}"),
+        }
+    }
+    let cfg = Arc::new(config::Config::load(
+        config_path.as_deref().map(std::path::Path::new),
+    )?);
+
+    let pool = db::connect(&cfg.database).await?;
+    let report = migrate::run(&pool, dry_run, now_unix()).await?;
+    let store = store::open(&cfg.database, &pool).await?;
+    let store_report = store.migrate(dry_run, now_unix()).await?;
+    if dry_run || migrate_only {
+        print_report("schema", &report, dry_run);
+        if let Some(report) = &store_report {
+            print_report("link store schema", report, dry_run);
+        }
+        return Ok(());
+    }
+    let joint = Arc::new(JointHub::new(cfg.joint.broadcast_buffer));
+    let locks = Arc::new(lock::LockGate::new());
+    let receipts = Arc::new(ReceiptHub::new());
+    let webhooks = Arc::new(webhook::Webhooks::new(
+        cfg.webhooks.clone(),
+        cfg.links.allow_private_urls,
+    )?);
+    let limits = Arc::new(ratelimit::RateLimiter::new(cfg.rate_limit.clone()));
+    let attachments = Arc::new(attachment::Attachments::new(cfg.attachments.clone())?);
+    let metrics = Arc::new(metrics::Metrics::new());
+    let state = Arc::new(AppState {
+        pool,
+        store,
+        config: cfg.clone(),
+        joint: joint.clone(),
+        locks: locks.clone(),
+        receipts: receipts.clone(),
+        webhooks: webhooks.clone(),
+        limits: limits.clone(),
+        url_policy: urlcheck::UrlPolicy {
+            allow_private: cfg.links.allow_private_urls,
+        },
+        denylist: Arc::new(denylist::Denylist::new(cfg.denylist.path.clone())?),
+        tracking: Arc::new(tracking::TrackingFilter::new(&cfg.tracking)?),
+        signets: Arc::new(signet::SignetRules::new(
+            cfg.signets.rules_path.as_deref(),
+            cfg.signets.mode,
+        )?),
+        signet_policy: registry::SignetPolicy {
+            mode: cfg.signets.mode,
+        },
+        attachments: attachments.clone(),
+        metrics: metrics.clone(),
+    });
 
     let joint_gc = joint.clone();
+    let gc_pool = state.pool.clone();
+    let gc_store = state.store.clone();
+    let joint_idle = Duration::from_secs(cfg.joint.idle_secs);
+    let gc_every = Duration::from_secs(cfg.sweep.gc_interval_secs);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(gc_every);
+        loop {
+            interval.tick().await;
+            joint_gc.cleanup(joint_idle).await;
+            locks.cleanup().await;
+            receipts.cleanup().await;
+            limits.cleanup().await;
+            attachments
+                .sweep(&gc_pool, gc_store.as_ref(), now_unix())
+                .await;
+        }
+    });
+
+    let sweep_state = state.clone();
+    let sweep = cfg.sweep.clone();
     tokio::spawn(async move {
-        let mut interval = tokio::time::interval(Duration::from_secs(30));
+        let mut interval = tokio::time::interval(Duration::from_secs(sweep.interval_secs));
         loop {
             interval.tick().await;
-            joint_gc.cleanup(Duration::from_secs(300)).await;
+            sweep_expired(&sweep_state, &sweep).await;
         }
     });
 
-    let cors = cors_layer();
+    if state.denylist.enabled() {
+        let deny_state = state.clone();
+        let every = cfg.denylist.reload_secs;
+        tokio::spawn(async move {
+            deny_state.denylist.apply(deny_state.store.as_ref()).await;
+            let mut interval = tokio::time::interval(Duration::from_secs(every));
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                match deny_state.denylist.reload_if_changed() {
+                    Ok(true) => deny_state.denylist.apply(deny_state.store.as_ref()).await,
+                    Ok(false) => {}
+                    Err(err) => tracing::error!(error = ?err, "denylist reload failed"),
+                }
+            }
+        });
+    }
+
+    let webhook_pool = state.pool.clone();
+    tokio::spawn(async move { webhooks.run(webhook_pool).await });
+
+    let cors = cors_layer(&cfg.server.cors_allow_origins);
+    // Room for the file plus the form around it.
+    let dispatch_limit = state.attachments.max_bytes().max(2 * 1024 * 1024) + 64 * 1024;
 
     let app = Router::new()
         .route("/healthz", get(health))
-        .route("/api/dispatch", post(dispatch_link))
-        .route("/api/joint", post(joint_create))
+        .route(
+            "/api/dispatch",
+            post(dispatch_handler.layer(DefaultBodyLimit::max(dispatch_limit))).layer(
+                middleware::from_fn_with_state(
+                    (state.clone(), ratelimit::Scope::Dispatch),
+                    rate_limit,
+                ),
+            ),
+        )
+        .route(
+            "/api/joint",
+            post(joint_create).layer(middleware::from_fn_with_state(
+                (state.clone(), ratelimit::Scope::Joint),
+                rate_limit,
+            )),
+        )
         .route("/api/joint/:id", get(joint_status))
         .route("/api/joint/:id/burn", post(joint_burn))
         .route("/api/joint/ws/:id", get(joint_ws_handler))
+        // Claims fill the callsigns table like links do: same bucket.
+        .route(
+            "/api/callsigns",
+            post(callsign_claim).layer(middleware::from_fn_with_state(
+                (state.clone(), ratelimit::Scope::Dispatch),
+                rate_limit,
+            )),
+        )
+        .route("/api/receipts/:id", get(receipt_handler))
         .route("/api/resolve/:slug", get(resolve_json))
+        .route("/api/resolve/:callsign/:slug", get(resolve_json))
         .route("/api/peek/:slug", get(peek_link))
+        .route("/api/peek/:callsign/:slug", get(peek_link))
         .route("/api/commence/:slug", post(commence_handler))
+        .route("/api/commence/:callsign/:slug", post(commence_handler))
+        .route("/api/unlock/:slug", post(unlock_handler))
+        .route("/api/unlock/:callsign/:slug", post(unlock_handler))
         .route("/api/burn/:slug", delete(burn_handler))
+        .route("/api/burn/:callsign/:slug", delete(burn_handler))
+        .route("/api/attachment/:slug", get(attachment_handler))
+        .route("/api/attachment/:callsign/:slug", get(attachment_handler))
+        .route("/api/signets", get(signets_list))
+        .route("/api/signets/detect", get(signet_detect))
+        .route("/api/qr/:slug", get(qr_handler))
+        .route("/api/qr/:callsign/:slug", get(qr_handler))
+        .route("/api/thread/:slug", get(thread_handler))
+        .route("/api/thread/:callsign/:slug", get(thread_handler))
+        .route("/api/links/:slug", patch(link_patch))
+        .route("/api/links/:callsign/:slug", patch(link_patch))
+        .route("/api/links/:slug/stats", get(link_stats))
+        .route("/api/links/:callsign/:slug/stats", get(link_stats))
         .route("/:slug", get(resolve_slug))
+        .route("/:callsign/:slug", get(resolve_slug))
+        .route_layer(middleware::from_fn_with_state(
+            metrics.clone(),
+            metrics::track,
+        ))
         .layer(TraceLayer::new_for_http())
         .layer(cors)
-        .with_state(state);
+        .with_state(state.clone());
+
+    // Scrapes get their own listener (keep it off the public interface).
+    if let Some(metrics_addr) = cfg.metrics.addr {
+        let metrics_app = Router::new()
+            .route("/metrics", get(metrics_handler))
+            .with_state(state);
+        let listener = tokio::net::TcpListener::bind(metrics_addr).await?;
+        tracing::info!("metrics listening on {metrics_addr}");
+        tokio::spawn(async move {
+            if let Err(err) = axum::serve(listener, metrics_app).await {
+                tracing::error!(error = ?err, "metrics listener failed");
+            }
+        });
+    }
 
-    let port: u16 = std::env::var("PORT")
-        .ok()
-        .and_then(|v| v.parse().ok())
-        .unwrap_or(3000);
-    let addr = std::net::SocketAddr::from(([0, 0, 0, 0], port));
+    let addr = std::net::SocketAddr::from(([0, 0, 0, 0], cfg.server.port));
     tracing::info!("amigo-api listening on {addr}");
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+    )
+    .await?;
     Ok(())
 }
@@ -0,0 +1,213 @@
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc, Mutex,
+    },
+    time::Instant,
+};
+
+// Seconds. Most routes answer from SQLite in a few ms; receipt long-polls
+// park for up to a minute.
+const BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0,
+];
+
+// name, type, help. Rendered in this order, series sorted inside.
+const FAMILIES: &[(&str, &str, &str)] = &[
+    (
+        "amigo_dispatches_total",
+        "counter",
+        "Links created, by content kind and signet.",
+    ),
+    (
+        "amigo_resolves_total",
+        "counter",
+        "Links resolved (redirect or JSON).",
+    ),
+    ("amigo_commences_total", "counter", "Views spent."),
+    (
+        "amigo_burns_total",
+        "counter",
+        "Links burned by their owner.",
+    ),
+    (
+        "amigo_not_found_total",
+        "counter",
+        "Requests answered 404, by route.",
+    ),
+    (
+        "amigo_slug_retries_total",
+        "counter",
+        "Random slug inserts retried after a failed attempt.",
+    ),
+    (
+        "amigo_broadcast_lagged_total",
+        "counter",
+        "Events a slow subscriber missed, by channel.",
+    ),
+    ("amigo_joint_rooms", "gauge", "Joint rooms alive."),
+    ("amigo_ws_connections", "gauge", "Open joint websockets."),
+    (
+        "amigo_http_request_duration_seconds",
+        "histogram",
+        "Request latency, by method and route.",
+    ),
+];
+
+type Labels = Vec<(&'static str, String)>;
+
+#[derive(Default)]
+struct Histogram {
+    // One slot per bucket, not cumulative; summed at render time.
+    buckets: Vec<u64>,
+    count: u64,
+    sum: f64,
+}
+
+// Prometheus text format, by hand: a few counters, two gauges, one
+// histogram. Label values are ours (route templates, known signets), so
+// the series count stays small.
+#[derive(Default)]
+pub struct Metrics {
+    counters: Mutex<BTreeMap<(&'static str, Labels), u64>>,
+    latency: Mutex<BTreeMap<Labels, Histogram>>,
+    ws_connections: AtomicI64,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn inc(&self, name: &'static str, labels: &[(&'static str, &str)]) {
+        self.add(name, labels, 1);
+    }
+
+    pub fn add(&self, name: &'static str, labels: &[(&'static str, &str)], n: u64) {
+        let labels = labels.iter().map(|(k, v)| (*k, v.to_string())).collect();
+        if let Ok(mut counters) = self.counters.lock() {
+            *counters.entry((name, labels)).or_default() += n;
+        }
+    }
+
+    // Held for as long as the socket is open.
+    pub fn ws_open(self: &Arc<Self>) -> WsGuard {
+        self.ws_connections.fetch_add(1, Ordering::Relaxed);
+        WsGuard(self.clone())
+    }
+
+    fn observe(&self, method: &str, route: &str, secs: f64) {
+        let labels = vec![("method", method.to_string()), ("route", route.to_string())];
+        let Ok(mut latency) = self.latency.lock() else {
+            return;
+        };
+        let hist = latency.entry(labels).or_default();
+        if hist.buckets.is_empty() {
+            hist.buckets = vec![0; BUCKETS.len()];
+        }
+        if let Some(slot) = BUCKETS.iter().position(|le| secs <= *le) {
+            hist.buckets[slot] += 1;
+        }
+        hist.count += 1;
+        hist.sum += secs;
+    }
+
+    pub fn render(&self, joint_rooms: usize) -> String {
+        let mut out = String::new();
+        let counters = self.counters.lock().map(|c| c.clone()).unwrap_or_default();
+        for (family, kind, help) in FAMILIES {
+            let _ = writeln!(out, "# HELP {family} {help}");
+            let _ = writeln!(out, "# TYPE {family} {kind}");
+            match *family {
+                "amigo_joint_rooms" => {
+                    let _ = writeln!(out, "{family} {joint_rooms}");
+                }
+                "amigo_ws_connections" => {
+                    let open = self.ws_connections.load(Ordering::Relaxed);
+                    let _ = writeln!(out, "{family} {open}");
+                }
+                "amigo_http_request_duration_seconds" => self.render_latency(&mut out, family),
+                _ => {
+                    for ((_, labels), value) in counters.iter().filter(|((n, _), _)| n == family) {
+                        let _ = writeln!(out, "{family}{} {value}", label_set(labels, None));
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    fn render_latency(&self, out: &mut String, family: &str) {
+        let Ok(latency) = self.latency.lock() else {
+            return;
+        };
+        for (labels, hist) in latency.iter() {
+            let mut seen = 0;
+            for (le, n) in BUCKETS.iter().zip(&hist.buckets) {
+                seen += n;
+                let set = label_set(labels, Some(&le.to_string()));
+                let _ = writeln!(out, "{family}_bucket{set} {seen}");
+            }
+            let set = label_set(labels, Some("+Inf"));
+            let _ = writeln!(out, "{family}_bucket{set} {}", hist.count);
+            let set = label_set(labels, None);
+            let _ = writeln!(out, "{family}_sum{set} {}", hist.sum);
+            let _ = writeln!(out, "{family}_count{set} {}", hist.count);
+        }
+    }
+}
+
+pub struct WsGuard(Arc<Metrics>);
+
+impl Drop for WsGuard {
+    fn drop(&mut self) {
+        self.0.ws_connections.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+fn label_set(labels: &Labels, le: Option<&str>) -> String {
+    let mut parts: Vec<String> = labels
+        .iter()
+        .map(|(k, v)| format!("{k}=\"{}\"", escape(v)))
+        .collect();
+    if let Some(le) = le {
+        parts.push(format!("le=\"{le}\""));
+    }
+    if parts.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", parts.join(","))
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+// Route layer: the matched template ("/api/qr/:slug"), never the raw path,
+// so slugs don't turn into series.
+pub async fn track(State(metrics): State<Arc<Metrics>>, req: Request, next: Next) -> Response {
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let method = req.method().to_string();
+    let started = Instant::now();
+    let response = next.run(req).await;
+    metrics.observe(&method, &route, started.elapsed().as_secs_f64());
+    if response.status() == axum::http::StatusCode::NOT_FOUND {
+        metrics.inc("amigo_not_found_total", &[("route", &route)]);
+    }
+    response
+}
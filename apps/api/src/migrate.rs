@@ -0,0 +1,430 @@
+use anyhow::Result;
+use sqlx::{Row, Sqlite, SqlitePool, Transaction};
+
+// The links table as of version 1. A macro so the legacy adoption can
+// build the same table under another name.
+macro_rules! links_table {
+    ($name:literal) => {
+        concat!(
+            "CREATE TABLE IF NOT EXISTS ",
+            $name,
+            r#" (
+          slug TEXT NOT NULL,
+          callsign TEXT NOT NULL DEFAULT '',
+          url TEXT,
+          note TEXT,
+          created_at INTEGER NOT NULL,
+          expires_at INTEGER,
+          views INTEGER DEFAULT 0,
+          max_views INTEGER,
+          reply_to TEXT,
+          emoji TEXT,
+          owner_hash TEXT,
+          envelope TEXT,
+          password_hash TEXT,
+          not_before INTEGER,
+          receipt_hash TEXT,
+          webhook_url TEXT,
+          webhook_secret TEXT,
+          blocked TEXT,
+          PRIMARY KEY (callsign, slug)
+        )"#
+        )
+    };
+}
+
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub up: &'static [&'static str],
+}
+
+// Append only. A shipped migration never changes: fix forward with a new
+// one. Statements run in order, in one transaction with the version row.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial layout",
+        up: &[
+            links_table!("links"),
+            "CREATE INDEX IF NOT EXISTS idx_links_expiry ON links(expires_at)",
+            "CREATE INDEX IF NOT EXISTS idx_links_reply ON links(reply_to)",
+            "CREATE INDEX IF NOT EXISTS idx_links_receipt ON links(receipt_hash)",
+            // Outbound webhook queue: survives restarts, retried with backoff.
+            // secret NULL = server-wide hook, signed with the configured secret.
+            r#"CREATE TABLE IF NOT EXISTS webhook_deliveries (
+          id INTEGER PRIMARY KEY AUTOINCREMENT,
+          url TEXT NOT NULL,
+          secret TEXT,
+          event TEXT NOT NULL,
+          payload TEXT NOT NULL,
+          attempts INTEGER NOT NULL DEFAULT 0,
+          next_attempt_at INTEGER NOT NULL,
+          created_at INTEGER NOT NULL,
+          last_error TEXT
+        )"#,
+            "CREATE INDEX IF NOT EXISTS idx_webhook_due ON webhook_deliveries(next_attempt_at)",
+            r#"CREATE TABLE IF NOT EXISTS callsigns (
+          name TEXT PRIMARY KEY,
+          owner_hash TEXT NOT NULL,
+          created_at INTEGER NOT NULL
+        )"#,
+        ],
+    },
+    Migration {
+        version: 2,
+        name: "attachments",
+        up: &[
+            // One file per link. data = SQLite store, file = name under
+            // attachments.dir. released_at: the link is gone, the file waits
+            // for the download its last commence granted.
+            r#"CREATE TABLE IF NOT EXISTS attachments (
+          id INTEGER PRIMARY KEY AUTOINCREMENT,
+          callsign TEXT NOT NULL,
+          slug TEXT NOT NULL,
+          content_type TEXT NOT NULL,
+          filename TEXT,
+          size INTEGER NOT NULL,
+          data BLOB,
+          file TEXT,
+          created_at INTEGER NOT NULL,
+          released_at INTEGER,
+          UNIQUE (callsign, slug)
+        )"#,
+        ],
+    },
+    Migration {
+        version: 3,
+        name: "link stats",
+        up: &[
+            // Opens only, as bare timestamps: no IPs, no agents.
+            r#"CREATE TABLE IF NOT EXISTS link_events (
+          callsign TEXT NOT NULL,
+          slug TEXT NOT NULL,
+          event TEXT NOT NULL,
+          ts INTEGER NOT NULL
+        )"#,
+            "CREATE INDEX IF NOT EXISTS idx_link_events ON link_events(callsign, slug, ts)",
+            // What's left of a consumed link, so its owner can still read the
+            // stats. Kept until the link would have expired.
+            r#"CREATE TABLE IF NOT EXISTS link_tombstones (
+          callsign TEXT NOT NULL,
+          slug TEXT NOT NULL,
+          owner_hash TEXT,
+          created_at INTEGER NOT NULL,
+          expires_at INTEGER,
+          max_views INTEGER,
+          views INTEGER NOT NULL,
+          consumed_at INTEGER NOT NULL,
+          PRIMARY KEY (callsign, slug)
+        )"#,
+        ],
+    },
+];
+
+const VERSION_TABLE: &str = r#"CREATE TABLE IF NOT EXISTS schema_version (
+          version INTEGER PRIMARY KEY,
+          name TEXT NOT NULL,
+          applied_at INTEGER NOT NULL
+        )"#;
+
+pub struct Report {
+    // Version before / after (dry run: after = what it would be).
+    pub from: i64,
+    pub to: i64,
+    pub applied: Vec<&'static Migration>,
+}
+
+// Brings the database up to the last migration. Dry run: the same work in
+// one transaction that is rolled back, so a broken migration still shows.
+pub async fn run(pool: &SqlitePool, dry_run: bool, now: i64) -> Result<Report> {
+    run_list(pool, MIGRATIONS, dry_run, now).await
+}
+
+async fn run_list(
+    pool: &SqlitePool,
+    migrations: &'static [Migration],
+    dry_run: bool,
+    now: i64,
+) -> Result<Report> {
+    let mut tx = pool.begin().await?;
+    let from = current_version(&mut tx, migrations).await?;
+    let pending: Vec<&'static Migration> = migrations.iter().filter(|m| m.version > from).collect();
+    let mut to = from;
+
+    for migration in &pending {
+        if from == 0 && migration.version == 1 && table_exists(&mut tx, "links").await? {
+            adopt_legacy(&mut tx).await?;
+        }
+        apply(&mut tx, migration, now).await?;
+        to = migration.version;
+        if dry_run {
+            tracing::info!(
+                version = migration.version,
+                name = migration.name,
+                "would migrate"
+            );
+        } else {
+            // One transaction per migration: a failure keeps the ones before.
+            tx.commit().await?;
+            tracing::info!(
+                version = migration.version,
+                name = migration.name,
+                "migrated"
+            );
+            tx = pool.begin().await?;
+        }
+    }
+
+    if dry_run {
+        tx.rollback().await?;
+    } else {
+        tx.commit().await?;
+    }
+    Ok(Report {
+        from,
+        to,
+        applied: pending,
+    })
+}
+
+async fn current_version(
+    tx: &mut Transaction<'_, Sqlite>,
+    migrations: &[Migration],
+) -> Result<i64> {
+    if !table_exists(tx, "schema_version").await? {
+        return Ok(0);
+    }
+    let version: Option<i64> = sqlx::query_scalar("SELECT MAX(version) FROM schema_version")
+        .fetch_one(&mut **tx)
+        .await?;
+    let version = version.unwrap_or(0);
+    if let Some(last) = migrations.last() {
+        if version > last.version {
+            anyhow::bail!(
+                "database schema is at version {version}, this build knows up to {}",
+                last.version
+            );
+        }
+    }
+    Ok(version)
+}
+
+async fn table_exists(tx: &mut Transaction<'_, Sqlite>, name: &str) -> Result<bool> {
+    let found: Option<i64> =
+        sqlx::query_scalar("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1")
+            .bind(name)
+            .fetch_optional(&mut **tx)
+            .await?;
+    Ok(found.is_some())
+}
+
+async fn apply(tx: &mut Transaction<'_, Sqlite>, migration: &Migration, now: i64) -> Result<()> {
+    sqlx::query(VERSION_TABLE).execute(&mut **tx).await?;
+    for sql in migration.up {
+        sqlx::query(sql).execute(&mut **tx).await.map_err(|err| {
+            anyhow::anyhow!(
+                "migration {} ({}) failed: {err}",
+                migration.version,
+                migration.name
+            )
+        })?;
+    }
+    sqlx::query("INSERT INTO schema_version (version, name, applied_at) VALUES (?1, ?2, ?3)")
+        .bind(migration.version)
+        .bind(migration.name)
+        .bind(now)
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
+
+// A database from before versioning: its links table is whatever layout
+// that build left (url NOT NULL, no views, slug-only key, any subset of
+// the later columns). Rebuilt once into the version 1 table, carrying over
+// the columns it has; the rest take their defaults.
+async fn adopt_legacy(tx: &mut Transaction<'_, Sqlite>) -> Result<()> {
+    sqlx::query("DROP TABLE IF EXISTS links_new")
+        .execute(&mut **tx)
+        .await?;
+    sqlx::query(links_table!("links_new"))
+        .execute(&mut **tx)
+        .await?;
+    let wanted = column_names(tx, "links_new").await?;
+    let carried: Vec<String> = column_names(tx, "links")
+        .await?
+        .into_iter()
+        .filter(|column| wanted.contains(column))
+        .collect();
+    let columns = carried.join(", ");
+    sqlx::query(&format!(
+        "INSERT INTO links_new ({columns}) SELECT {columns} FROM links"
+    ))
+    .execute(&mut **tx)
+    .await?;
+    sqlx::query("DROP TABLE links").execute(&mut **tx).await?;
+    sqlx::query("ALTER TABLE links_new RENAME TO links")
+        .execute(&mut **tx)
+        .await?;
+    tracing::info!(columns = carried.len(), "legacy links table adopted");
+    Ok(())
+}
+
+async fn column_names(tx: &mut Transaction<'_, Sqlite>, table: &str) -> Result<Vec<String>> {
+    let rows = sqlx::query(&format!("PRAGMA table_info({table})"))
+        .fetch_all(&mut **tx)
+        .await?;
+    rows.iter()
+        .map(|row| row.try_get::<String, _>("name").map_err(Into::into))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::DatabaseConfig, db, token};
+
+    async fn pool(url: &str) -> SqlitePool {
+        let cfg = DatabaseConfig {
+            url: url.to_string(),
+            max_connections: 1,
+            ..DatabaseConfig::default()
+        };
+        db::connect(&cfg).await.unwrap()
+    }
+
+    // Every table's columns and every index, in a stable order.
+    async fn schema(pool: &SqlitePool) -> Vec<String> {
+        let objects: Vec<(String, String, String)> = sqlx::query_as(
+            "SELECT type, name, tbl_name FROM sqlite_master \
+             WHERE name NOT LIKE 'sqlite_%' ORDER BY type, name",
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap();
+        let mut out = Vec::new();
+        for (kind, name, table) in objects {
+            if kind != "table" {
+                out.push(format!("{kind} {name} on {table}"));
+                continue;
+            }
+            let columns: Vec<(String, String, i64, Option<String>, i64)> = sqlx::query_as(
+                &format!("SELECT name, type, \"notnull\", dflt_value, pk FROM pragma_table_info('{name}')"),
+            )
+            .fetch_all(pool)
+            .await
+            .unwrap();
+            out.push(format!("table {name} {columns:?}"));
+        }
+        out
+    }
+
+    async fn version(pool: &SqlitePool) -> Option<i64> {
+        sqlx::query_scalar("SELECT MAX(version) FROM schema_version")
+            .fetch_one(pool)
+            .await
+            .ok()
+            .flatten()
+    }
+
+    #[tokio::test]
+    async fn baseline_layout_upgrades_to_the_fresh_schema() {
+        let fresh = pool("sqlite::memory:").await;
+        let report = run(&fresh, false, 1).await.unwrap();
+        assert_eq!((report.from, report.to), (0, 3));
+
+        // What the old ensure_schema left behind: slug-only key, no
+        // callsigns, owners or anything later.
+        let old = pool("sqlite::memory:").await;
+        for sql in [
+            r#"CREATE TABLE links (
+              slug TEXT PRIMARY KEY,
+              url TEXT,
+              note TEXT,
+              created_at INTEGER NOT NULL,
+              expires_at INTEGER,
+              views INTEGER DEFAULT 0,
+              max_views INTEGER,
+              reply_to TEXT,
+              emoji TEXT
+            )"#,
+            "CREATE INDEX idx_links_expiry ON links(expires_at)",
+            "INSERT INTO links (slug, url, note, created_at, expires_at, views, emoji) \
+             VALUES ('abc123', 'https://example.com/', 'hi', 5, 99, 2, '📦')",
+        ] {
+            sqlx::query(sql).execute(&old).await.unwrap();
+        }
+        let report = run(&old, false, 1).await.unwrap();
+        assert_eq!((report.from, report.to), (0, 3));
+        assert_eq!(version(&old).await, Some(3));
+        assert_eq!(schema(&old).await, schema(&fresh).await);
+
+        let row: (String, String, Option<String>, i64, Option<String>) =
+            sqlx::query_as("SELECT callsign, slug, note, views, owner_hash FROM links")
+                .fetch_one(&old)
+                .await
+                .unwrap();
+        assert_eq!(
+            row,
+            (String::new(), "abc123".into(), Some("hi".into()), 2, None)
+        );
+
+        // Up to date: nothing more to do.
+        let again = run(&old, false, 2).await.unwrap();
+        assert_eq!((again.from, again.to, again.applied.len()), (3, 3, 0));
+    }
+
+    static BROKEN: &[Migration] = &[
+        Migration {
+            version: 1,
+            name: "fine",
+            up: &["CREATE TABLE one (id INTEGER)"],
+        },
+        Migration {
+            version: 2,
+            name: "broken",
+            up: &[
+                "CREATE TABLE two (id INTEGER)",
+                "INSERT INTO nowhere VALUES (1)",
+            ],
+        },
+    ];
+
+    #[tokio::test]
+    async fn failing_migration_rolls_back_to_the_last_good_one() {
+        let pool = pool("sqlite::memory:").await;
+        let Err(err) = run_list(&pool, BROKEN, false, 1).await else {
+            panic!("broken migration applied");
+        };
+        assert!(err.to_string().contains("migration 2 (broken)"), "{err}");
+
+        assert_eq!(version(&pool).await, Some(1));
+        let tables: Vec<String> = sqlx::query_scalar(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name IN ('one', 'two')",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(tables, ["one"]);
+    }
+
+    #[tokio::test]
+    async fn dry_run_leaves_the_file_untouched() {
+        let path = std::env::temp_dir().join(format!("amigo-migrate-{}.db", token::gen_token()));
+        let url = format!("sqlite:{}", path.display());
+        let pool = pool(&url).await;
+        sqlx::query("CREATE TABLE links (slug TEXT PRIMARY KEY, url TEXT NOT NULL)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let before = schema(&pool).await;
+        let bytes = std::fs::read(&path).unwrap();
+
+        let report = run(&pool, true, 1).await.unwrap();
+        assert_eq!((report.from, report.to, report.applied.len()), (0, 3, 3));
+        assert_eq!(schema(&pool).await, before);
+        pool.close().await;
+        assert_eq!(std::fs::read(&path).unwrap(), bytes);
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,227 @@
+use qrcode::{Color, EcLevel, QrCode};
+
+pub const DEFAULT_SIZE: u32 = 256;
+pub const MIN_SIZE: u32 = 64;
+pub const MAX_SIZE: u32 = 2048;
+pub const DEFAULT_MARGIN: u32 = 4;
+pub const MAX_MARGIN: u32 = 16;
+
+// Share of the symbol the centre signet may cover. H restores ~30%, so a
+// fifth of the width (4% of the area) leaves plenty for smudged paper.
+const SIGNET_RATIO: f64 = 0.2;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Svg,
+    Png,
+}
+
+pub struct QrOptions {
+    // Output width in px (PNG rounds down to whole pixels per module).
+    pub size: u32,
+    // Quiet zone, in modules.
+    pub margin: u32,
+    pub ecc: EcLevel,
+    pub signet: Option<String>,
+}
+
+pub fn parse_ecc(raw: &str) -> Option<EcLevel> {
+    match raw.trim().to_ascii_uppercase().as_str() {
+        "L" => Some(EcLevel::L),
+        "M" => Some(EcLevel::M),
+        "Q" => Some(EcLevel::Q),
+        "H" => Some(EcLevel::H),
+        _ => None,
+    }
+}
+
+struct Matrix {
+    width: usize,
+    dark: Vec<bool>,
+}
+
+impl Matrix {
+    fn encode(data: &str, ecc: EcLevel) -> Result<Self, String> {
+        let code = QrCode::with_error_correction_level(data.as_bytes(), ecc)
+            .map_err(|err| format!("qr encode failed: {err}"))?;
+        Ok(Self {
+            width: code.width(),
+            dark: code
+                .to_colors()
+                .into_iter()
+                .map(|color| color == Color::Dark)
+                .collect(),
+        })
+    }
+
+    fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.dark[y * self.width + x]
+    }
+}
+
+pub fn render_svg(data: &str, opts: &QrOptions) -> Result<String, String> {
+    let matrix = Matrix::encode(data, opts.ecc)?;
+    let margin = opts.margin as usize;
+    let total = matrix.width + 2 * margin;
+
+    let mut path = String::new();
+    for y in 0..matrix.width {
+        for x in 0..matrix.width {
+            if matrix.is_dark(x, y) {
+                path.push_str(&format!("M{} {}h1v1h-1z", x + margin, y + margin));
+            }
+        }
+    }
+
+    let mut svg = format!(
+        concat!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{size}" height="{size}" "#,
+            r#"viewBox="0 0 {total} {total}" shape-rendering="crispEdges">"#,
+            r##"<rect width="{total}" height="{total}" fill="#fff"/>"##,
+            r##"<path fill="#000" d="{path}"/>"##
+        ),
+        size = opts.size,
+        total = total,
+        path = path,
+    );
+
+    if let Some(signet) = opts.signet.as_deref() {
+        let badge = (total as f64 * SIGNET_RATIO).round();
+        let origin = (total as f64 - badge) / 2.0;
+        let centre = total as f64 / 2.0;
+        svg.push_str(&format!(
+            concat!(
+                r##"<rect x="{origin}" y="{origin}" width="{badge}" height="{badge}" rx="{radius}" fill="#fff"/>"##,
+                r#"<text x="{centre}" y="{centre}" font-size="{font}" text-anchor="middle" "#,
+                r#"dominant-baseline="central">{signet}</text>"#
+            ),
+            origin = origin,
+            badge = badge,
+            radius = badge / 4.0,
+            centre = centre,
+            font = badge * 0.75,
+            signet = escape_xml(signet),
+        ));
+    }
+
+    svg.push_str("</svg>");
+    Ok(svg)
+}
+
+// Black on white, 8-bit grayscale. No fonts on the server, so no signet.
+pub fn render_png(data: &str, opts: &QrOptions) -> Result<Vec<u8>, String> {
+    let matrix = Matrix::encode(data, opts.ecc)?;
+    let margin = opts.margin as usize;
+    let total = matrix.width + 2 * margin;
+    let scale = (opts.size as usize / total).max(1);
+    let side = total * scale;
+
+    let mut pixels = vec![255u8; side * side];
+    for y in 0..matrix.width {
+        for x in 0..matrix.width {
+            if !matrix.is_dark(x, y) {
+                continue;
+            }
+            for dy in 0..scale {
+                let row = ((y + margin) * scale + dy) * side;
+                let start = row + (x + margin) * scale;
+                pixels[start..start + scale].fill(0);
+            }
+        }
+    }
+
+    let mut out = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut out, side as u32, side as u32);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder
+            .write_header()
+            .map_err(|err| format!("png encode failed: {err}"))?;
+        writer
+            .write_image_data(&pixels)
+            .map_err(|err| format!("png encode failed: {err}"))?;
+    }
+    Ok(out)
+}
+
+fn escape_xml(input: &str) -> String {
+    input
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINK: &str = "https://amigo.sh/abc123";
+
+    fn opts(size: u32, margin: u32, signet: Option<&str>) -> QrOptions {
+        QrOptions {
+            size,
+            margin,
+            ecc: EcLevel::M,
+            signet: signet.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn ecc_levels_any_case() {
+        for (raw, want) in [
+            ("L", Some(EcLevel::L)),
+            ("m", Some(EcLevel::M)),
+            (" q ", Some(EcLevel::Q)),
+            ("h", Some(EcLevel::H)),
+            ("", None),
+            ("x", None),
+            ("HH", None),
+            ("high", None),
+        ] {
+            assert_eq!(parse_ecc(raw), want, "{raw:?}");
+        }
+    }
+
+    #[test]
+    fn png_rounds_down_to_whole_modules() {
+        let modules = Matrix::encode(LINK, EcLevel::M).unwrap().width;
+        for (size, margin) in [(256, 4), (MIN_SIZE, 0), (1000, 16), (MAX_SIZE, 4)] {
+            let total = modules + 2 * margin as usize;
+            let png = render_png(LINK, &opts(size, margin, None)).unwrap();
+            let mut reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+            let mut pixels = vec![0; reader.output_buffer_size()];
+            let frame = reader.next_frame(&mut pixels).unwrap();
+
+            let side = frame.width as usize;
+            assert_eq!(frame.height as usize, side);
+            assert_eq!(frame.color_type, png::ColorType::Grayscale);
+            let scale = (size as usize / total).max(1);
+            assert_eq!(side, total * scale, "size {size}, margin {margin}");
+            assert!(side <= (size as usize).max(total));
+
+            // Quiet zone white, finder pattern corner black.
+            let at = |x: usize, y: usize| pixels[y * frame.line_size + x];
+            assert_eq!(at(0, 0), if margin == 0 { 0 } else { 255 });
+            let corner = margin as usize * scale;
+            assert_eq!(at(corner, corner), 0);
+        }
+    }
+
+    #[test]
+    fn svg_escapes_the_signet() {
+        let svg = render_svg(LINK, &opts(300, 4, Some(r#"<b>&"🐸'"#))).unwrap();
+        assert!(svg.starts_with("<svg ") && svg.ends_with("</svg>"));
+        assert!(svg.contains(r#"width="300" height="300""#));
+        assert!(
+            svg.contains(">&lt;b&gt;&amp;&quot;🐸&apos;</text>"),
+            "{svg}"
+        );
+        assert!(!svg.contains("<b>"));
+
+        let plain = render_svg(LINK, &opts(300, 4, None)).unwrap();
+        assert!(!plain.contains("<text"));
+    }
+}
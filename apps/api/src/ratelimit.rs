@@ -0,0 +1,260 @@
+use axum::http::HeaderMap;
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    time::{Duration, Instant},
+};
+use tokio::sync::RwLock;
+
+use crate::{config::RateLimitConfig, token, ttl};
+
+// Routes that create things: links fill SQLite, joints fill JointHub.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Scope {
+    Dispatch,
+    Joint,
+}
+
+impl Scope {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Dispatch => "dispatch",
+            Self::Joint => "joint",
+        }
+    }
+}
+
+// "30/1m": bursts of up to 30, refilled at 30 per minute.
+#[derive(Clone, Copy, Debug)]
+pub struct Rule {
+    pub burst: u32,
+    pub period: Duration,
+}
+
+impl Rule {
+    pub fn parse(raw: &str) -> Result<Option<Self>, String> {
+        let raw = raw.trim();
+        if raw.is_empty() || raw == "0" || raw.eq_ignore_ascii_case("off") {
+            return Ok(None);
+        }
+        let (burst, period) = raw
+            .split_once('/')
+            .ok_or_else(|| format!("expected <count>/<period>, got {raw}"))?;
+        let burst: u32 = burst
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid count in {raw}"))?;
+        if burst == 0 {
+            return Ok(None);
+        }
+        let secs = ttl::parse_duration(period)?;
+        Ok(Some(Self {
+            burst,
+            period: Duration::from_secs(secs as u64),
+        }))
+    }
+
+    fn refill_per_sec(&self) -> f64 {
+        self.burst as f64 / self.period.as_secs_f64()
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+// Token buckets per (route, client). A client is its IP and, when it sends
+// a Bearer key, that key too; both buckets must have a token to spare.
+pub struct RateLimiter {
+    cfg: RateLimitConfig,
+    buckets: RwLock<HashMap<(Scope, String), Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(cfg: RateLimitConfig) -> Self {
+        Self {
+            cfg,
+            buckets: RwLock::new(HashMap::new()),
+        }
+    }
+
+    fn rule(&self, scope: Scope) -> Option<Rule> {
+        match scope {
+            Scope::Dispatch => self.cfg.dispatch,
+            Scope::Joint => self.cfg.joint,
+        }
+    }
+
+    // The address the last trusted proxy saw. With N hops, that's the Nth
+    // X-Forwarded-For entry from the right; anything left of it is
+    // client-supplied and can't be trusted.
+    pub fn client_ip(&self, peer: SocketAddr, headers: &HeaderMap) -> IpAddr {
+        if self.cfg.trusted_proxy_hops == 0 {
+            return peer.ip();
+        }
+        let forwarded: Vec<&str> = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .collect();
+        // Fewer entries than proxies: the request skipped them, use the peer.
+        let Some(index) = forwarded.len().checked_sub(self.cfg.trusted_proxy_hops) else {
+            return peer.ip();
+        };
+        forwarded
+            .get(index)
+            .and_then(|entry| entry.parse().ok())
+            .unwrap_or_else(|| peer.ip())
+    }
+
+    // Ok = go ahead; Err(wait) = come back after `wait`.
+    pub async fn check(
+        &self,
+        scope: Scope,
+        peer: SocketAddr,
+        headers: &HeaderMap,
+    ) -> Result<(), Duration> {
+        let Some(rule) = self.rule(scope) else {
+            return Ok(());
+        };
+        let mut keys = vec![format!("ip:{}", self.client_ip(peer, headers))];
+        if let Some(key) = token::bearer(headers) {
+            keys.push(format!("key:{}", token::hash_token(key)));
+        }
+
+        let now = Instant::now();
+        let mut buckets = self.buckets.write().await;
+        let mut wait = Duration::ZERO;
+        for key in &keys {
+            let bucket = buckets.entry((scope, key.clone())).or_insert(Bucket {
+                tokens: rule.burst as f64,
+                updated: now,
+            });
+            let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+            bucket.tokens =
+                (bucket.tokens + elapsed * rule.refill_per_sec()).min(rule.burst as f64);
+            bucket.updated = now;
+            if bucket.tokens < 1.0 {
+                let missing = (1.0 - bucket.tokens) / rule.refill_per_sec();
+                wait = wait.max(Duration::from_secs_f64(missing));
+            }
+        }
+        if !wait.is_zero() {
+            tracing::debug!(scope = scope.name(), "rate limited");
+            return Err(wait);
+        }
+        for key in keys {
+            if let Some(bucket) = buckets.get_mut(&(scope, key)) {
+                bucket.tokens -= 1.0;
+            }
+        }
+        Ok(())
+    }
+
+    // Forget buckets that have refilled completely; they'd start full anyway.
+    pub async fn cleanup(&self) {
+        let now = Instant::now();
+        self.buckets.write().await.retain(|(scope, _), bucket| {
+            self.rule(*scope)
+                .is_some_and(|rule| now.duration_since(bucket.updated) < rule.period)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn parses_count_per_period() {
+        for (raw, want) in [
+            ("30/1m", Some((30, 60))),
+            (" 10 / 1h ", Some((10, 3600))),
+            ("5/1d12h", Some((5, 36 * 3600))),
+            ("1/PT90S", Some((1, 90))),
+            ("off", None),
+            ("OFF", None),
+            ("0", None),
+            ("", None),
+            ("0/1m", None),
+        ] {
+            let got = Rule::parse(raw)
+                .unwrap()
+                .map(|rule| (rule.burst, rule.period.as_secs()));
+            assert_eq!(got, want, "{raw:?}");
+        }
+        for raw in ["30", "30/", "/1m", "x/1m", "-1/1m", "30/1y", "30/soon"] {
+            assert!(Rule::parse(raw).is_err(), "{raw:?} accepted");
+        }
+    }
+
+    fn limiter(hops: usize) -> RateLimiter {
+        RateLimiter::new(RateLimitConfig {
+            trusted_proxy_hops: hops,
+            ..RateLimitConfig::default()
+        })
+    }
+
+    #[test]
+    fn client_ip_trusts_only_the_proxy_hops() {
+        let peer: SocketAddr = "10.0.0.1:4000".parse().unwrap();
+        for (hops, forwarded, want) in [
+            // No proxy: the header is whatever the client made up.
+            (0, &["6.6.6.6"][..], "10.0.0.1"),
+            (1, &[], "10.0.0.1"),
+            (1, &["203.0.113.7"], "203.0.113.7"),
+            // Spoofed entries sit left of what our proxy appended.
+            (1, &["6.6.6.6, 203.0.113.7"], "203.0.113.7"),
+            (1, &["6.6.6.6", "203.0.113.7"], "203.0.113.7"),
+            (2, &["6.6.6.6, 203.0.113.7, 10.1.1.1"], "203.0.113.7"),
+            // Fewer entries than proxies: they were skipped.
+            (2, &["203.0.113.7"], "10.0.0.1"),
+            (1, &["not-an-ip"], "10.0.0.1"),
+            (1, &["2001:db8::1"], "2001:db8::1"),
+            (1, &[" , 203.0.113.7 ,"], "203.0.113.7"),
+        ] {
+            let mut headers = HeaderMap::new();
+            for value in forwarded {
+                headers.append("x-forwarded-for", HeaderValue::from_static(value));
+            }
+            let got = limiter(hops).client_ip(peer, &headers);
+            assert_eq!(got.to_string(), want, "hops {hops}, {forwarded:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn burst_then_wait_per_client_and_key() {
+        let limits = RateLimiter::new(RateLimitConfig {
+            dispatch: Rule::parse("2/1m").unwrap(),
+            ..RateLimitConfig::default()
+        });
+        let alice: SocketAddr = "198.51.100.1:1".parse().unwrap();
+        let bob: SocketAddr = "198.51.100.2:1".parse().unwrap();
+        let none = HeaderMap::new();
+
+        assert!(limits.check(Scope::Dispatch, alice, &none).await.is_ok());
+        assert!(limits.check(Scope::Dispatch, alice, &none).await.is_ok());
+        let wait = limits
+            .check(Scope::Dispatch, alice, &none)
+            .await
+            .unwrap_err();
+        assert!(wait > Duration::from_secs(25) && wait <= Duration::from_secs(30));
+        // Other clients and other routes have their own buckets.
+        assert!(limits.check(Scope::Dispatch, bob, &none).await.is_ok());
+        assert!(limits.check(Scope::Joint, alice, &none).await.is_ok());
+
+        // One key from two addresses still shares a bucket.
+        let mut keyed = HeaderMap::new();
+        keyed.insert("authorization", HeaderValue::from_static("Bearer k"));
+        let carol: SocketAddr = "198.51.100.3:1".parse().unwrap();
+        let dave: SocketAddr = "198.51.100.4:1".parse().unwrap();
+        assert!(limits.check(Scope::Dispatch, carol, &keyed).await.is_ok());
+        assert!(limits.check(Scope::Dispatch, dave, &keyed).await.is_ok());
+        assert!(limits.check(Scope::Dispatch, dave, &keyed).await.is_err());
+    }
+}
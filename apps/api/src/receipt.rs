@@ -0,0 +1,158 @@
+use serde::Serialize;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::sync::{broadcast, RwLock};
+
+// Events kept per receipt so a late (or reconnecting) listener still hears
+// what happened. A link only ever produces a handful.
+const HISTORY: usize = 16;
+const BUFFER: usize = 16;
+// After the final event, keep the channel around long enough to be read.
+const FINISHED_GRACE: Duration = Duration::from_secs(10 * 60);
+const IDLE_LIMIT: Duration = Duration::from_secs(7 * 24 * 3600);
+
+// What the sender gets to know: that it happened and when. Never who.
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ReceiptKind {
+    Opened { views: i64, remaining: Option<i64> },
+    Burned,
+    Expired,
+}
+
+impl ReceiptKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Opened { .. } => "opened",
+            Self::Burned => "burned",
+            Self::Expired => "expired",
+        }
+    }
+
+    // Burned / expired: the petal is gone, nothing more will follow.
+    pub fn is_final(&self) -> bool {
+        !matches!(self, Self::Opened { .. })
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ReceiptEvent {
+    pub seq: u64,
+    pub ts: i64,
+    #[serde(flatten)]
+    pub kind: ReceiptKind,
+}
+
+struct Log {
+    seq: u64,
+    history: VecDeque<ReceiptEvent>,
+    finished: bool,
+    last_activity: Instant,
+}
+
+struct Channel {
+    tx: broadcast::Sender<ReceiptEvent>,
+    log: Mutex<Log>,
+}
+
+pub struct Subscription {
+    // Already-happened events after the requested seq, oldest first.
+    pub backlog: Vec<ReceiptEvent>,
+    pub finished: bool,
+    pub rx: broadcast::Receiver<ReceiptEvent>,
+}
+
+// Read receipts, keyed by the receipt id hash (same as the DB column).
+// In memory like the joint rooms: a restart forgets the history, later
+// events still arrive.
+pub struct ReceiptHub {
+    channels: RwLock<HashMap<String, Arc<Channel>>>,
+}
+
+impl ReceiptHub {
+    pub fn new() -> Self {
+        Self {
+            channels: RwLock::new(HashMap::new()),
+        }
+    }
+
+    async fn channel(&self, hash: &str) -> Arc<Channel> {
+        if let Some(channel) = self.channels.read().await.get(hash) {
+            return channel.clone();
+        }
+        let mut channels = self.channels.write().await;
+        channels
+            .entry(hash.to_string())
+            .or_insert_with(|| {
+                let (tx, _rx) = broadcast::channel(BUFFER);
+                Arc::new(Channel {
+                    tx,
+                    log: Mutex::new(Log {
+                        seq: 0,
+                        history: VecDeque::new(),
+                        finished: false,
+                        last_activity: Instant::now(),
+                    }),
+                })
+            })
+            .clone()
+    }
+
+    pub async fn known(&self, hash: &str) -> bool {
+        self.channels.read().await.contains_key(hash)
+    }
+
+    pub async fn emit(&self, hash: &str, kind: ReceiptKind, ts: i64) {
+        let channel = self.channel(hash).await;
+        let mut log = channel.log.lock().unwrap();
+        if log.finished {
+            return;
+        }
+        log.seq += 1;
+        log.finished = kind.is_final();
+        log.last_activity = Instant::now();
+        let event = ReceiptEvent {
+            seq: log.seq,
+            ts,
+            kind,
+        };
+        if log.history.len() == HISTORY {
+            log.history.pop_front();
+        }
+        log.history.push_back(event.clone());
+        // Sent under the log lock so a concurrent subscribe sees each event
+        // exactly once: either in its backlog or on its receiver.
+        let _ = channel.tx.send(event);
+    }
+
+    pub async fn subscribe(&self, hash: &str, after: u64) -> Subscription {
+        let channel = self.channel(hash).await;
+        let mut log = channel.log.lock().unwrap();
+        log.last_activity = Instant::now();
+        Subscription {
+            backlog: log
+                .history
+                .iter()
+                .filter(|event| event.seq > after)
+                .cloned()
+                .collect(),
+            finished: log.finished,
+            rx: channel.tx.subscribe(),
+        }
+    }
+
+    pub async fn cleanup(&self) {
+        self.channels.write().await.retain(|_, channel| {
+            let log = channel.log.lock().unwrap();
+            let idle = log.last_activity.elapsed();
+            if log.finished {
+                idle < FINISHED_GRACE
+            } else {
+                idle < IDLE_LIMIT || channel.tx.receiver_count() > 0
+            }
+        });
+    }
+}
@@ -0,0 +1,306 @@
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+
+// Longest grapheme we take, in code points: ZWJ families with skin tones
+// fit, a cluster with two hundred combining marks doesn't.
+const MAX_GRAPHEME_CHARS: usize = 16;
+// Legacy cap for SIGNET_MODE=free.
+const MAX_FREE_BYTES: usize = 32;
+
+#[derive(Serialize, Clone, Copy)]
+pub struct Descriptions {
+    pub ro: &'static str,
+    pub en: &'static str,
+    pub es: &'static str,
+}
+
+#[derive(Serialize, Clone, Copy)]
+pub struct Signet {
+    pub glyph: &'static str,
+    pub key: &'static str,
+    pub name: &'static str,
+    // Canonical meaning, as written in the Protocol Set.
+    pub meaning: &'static str,
+    pub descriptions: Descriptions,
+}
+
+// The Protocol Set. Sigiliul nu spune o poveste. Deschide o stare.
+pub const PROTOCOL_SET: &[Signet] = &[
+    Signet {
+        glyph: "🚬",
+        key: "ground",
+        name: "The Ground",
+        meaning: "Pauză, Adevăr, Sediment",
+        descriptions: Descriptions {
+            ro: "Pauză, adevăr, sediment.",
+            en: "Pause, truth, sediment.",
+            es: "Pausa, verdad, sedimento.",
+        },
+    },
+    Signet {
+        glyph: "🐺",
+        key: "pack",
+        name: "The Pack",
+        meaning: "Loialitate, Protecție, Instinct",
+        descriptions: Descriptions {
+            ro: "Loialitate, protecție, instinct.",
+            en: "Loyalty, protection, instinct.",
+            es: "Lealtad, protección, instinto.",
+        },
+    },
+    Signet {
+        glyph: "🐸",
+        key: "signal",
+        name: "The Signal",
+        meaning: "Croac, Frecvență, Conexiune",
+        descriptions: Descriptions {
+            ro: "Semnal, frecvență, conexiune.",
+            en: "Signal, frequency, connection.",
+            es: "Señal, frecuencia, conexión.",
+        },
+    },
+    Signet {
+        glyph: "🌸",
+        key: "petal",
+        name: "The Petal",
+        meaning: "Efemer, Blândețe, Prezent",
+        descriptions: Descriptions {
+            ro: "Blând, efemer, prezent.",
+            en: "Gentle, ephemeral, present.",
+            es: "Suave, efímero, presente.",
+        },
+    },
+    Signet {
+        glyph: "🦅",
+        key: "vision",
+        name: "The Vision",
+        meaning: "Perspectivă, Altitudine, Claritate",
+        descriptions: Descriptions {
+            ro: "Altitudine, claritate, perspectivă.",
+            en: "Altitude, clarity, perspective.",
+            es: "Altitud, claridad, perspectiva.",
+        },
+    },
+    Signet {
+        glyph: "🐻",
+        key: "strength",
+        name: "The Strength",
+        meaning: "Putere calmă, Hibernare, Grijă",
+        descriptions: Descriptions {
+            ro: "Putere calmă, grijă, odihnă.",
+            en: "Calm strength, care, rest.",
+            es: "Fuerza calma, cuidado, reposo.",
+        },
+    },
+    Signet {
+        glyph: "🛰️",
+        key: "channel",
+        name: "The Channel",
+        meaning: "Tehnic, Distanță, Transmisie",
+        descriptions: Descriptions {
+            ro: "Tehnic, distanță, transmisie.",
+            en: "Technical, distance, transmission.",
+            es: "Técnico, distancia, transmisión.",
+        },
+    },
+    Signet {
+        glyph: "⚓",
+        key: "anchor",
+        name: "The Anchor",
+        meaning: "Stabilitate, Buoy, Siguranță",
+        descriptions: Descriptions {
+            ro: "Stabilitate, ancorare, siguranță.",
+            en: "Stability, grounding, safety.",
+            es: "Estabilidad, anclaje, seguridad.",
+        },
+    },
+    Signet {
+        glyph: "🫧",
+        key: "bubbles",
+        name: "The Bubbles",
+        meaning: "Biluțe, Joacă, Aer",
+        descriptions: Descriptions {
+            ro: "Joacă, aer, lejeritate.",
+            en: "Play, air, lightness.",
+            es: "Juego, aire, ligereza.",
+        },
+    },
+    Signet {
+        glyph: "💖",
+        key: "gratia",
+        name: "The Gratia",
+        meaning: "Grijă, Inimă, Blândețe",
+        descriptions: Descriptions {
+            ro: "Grijă, inimă, blândețe.",
+            en: "Care, heart, warmth.",
+            es: "Cuidado, corazón, ternura.",
+        },
+    },
+    Signet {
+        glyph: "👍",
+        key: "yes",
+        name: "The Yes",
+        meaning: "Da, Merge, Confirmare",
+        descriptions: Descriptions {
+            ro: "Da, ok, confirmare.",
+            en: "Yes, ok, confirmed.",
+            es: "Sí, ok, confirmación.",
+        },
+    },
+    Signet {
+        glyph: "📻",
+        key: "song",
+        name: "The Song",
+        meaning: "Muzică, Ritm, Drum",
+        descriptions: Descriptions {
+            ro: "Muzică, ritm, flow.",
+            en: "Music, rhythm, flow.",
+            es: "Música, ritmo, flujo.",
+        },
+    },
+];
+
+// "🛰" and "🛰️" are the same signet; VS16 only asks for emoji presentation.
+fn bare(glyph: &str) -> String {
+    glyph.chars().filter(|&c| c != '\u{FE0F}').collect()
+}
+
+// By glyph (with or without VS16) or by key ("pack").
+pub fn lookup(input: &str) -> Option<&'static Signet> {
+    let input = input.trim();
+    let wanted = bare(input);
+    PROTOCOL_SET
+        .iter()
+        .find(|signet| bare(signet.glyph) == wanted || signet.key.eq_ignore_ascii_case(input))
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignetMode {
+    // Anything printable up to 32 bytes (the old behaviour).
+    Free,
+    // Exactly one grapheme cluster: one emoji, flag or letter.
+    Grapheme,
+    // Only the Protocol Set.
+    Registry,
+}
+
+#[derive(Clone)]
+pub struct SignetPolicy {
+    pub mode: SignetMode,
+}
+
+impl SignetPolicy {
+    // Ok(None) = no signet given, let detection pick. Free mode drops what
+    // it can't use, like it always did; the strict modes say no.
+    pub fn check(&self, input: Option<String>) -> Result<Option<String>, &'static str> {
+        let Some(value) = input else {
+            return Ok(None);
+        };
+        let value = value.trim();
+        if value.is_empty() {
+            return Ok(None);
+        }
+        let printable = !value.chars().any(char::is_control);
+        match self.mode {
+            SignetMode::Free => {
+                Ok((printable && value.len() <= MAX_FREE_BYTES).then(|| value.to_string()))
+            }
+            SignetMode::Grapheme => {
+                if printable
+                    && value.graphemes(true).count() == 1
+                    && value.chars().count() <= MAX_GRAPHEME_CHARS
+                {
+                    Ok(Some(value.to_string()))
+                } else {
+                    Err("emoji must be a single grapheme\n")
+                }
+            }
+            SignetMode::Registry => lookup(value)
+                .map(|signet| Some(signet.glyph.to_string()))
+                .ok_or("emoji must be a registered signet (GET /api/signets)\n"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(mode: SignetMode, input: &str) -> Result<Option<String>, &'static str> {
+        SignetPolicy { mode }.check(Some(input.to_string()))
+    }
+
+    #[test]
+    fn free_mode_drops_what_it_cannot_use() {
+        let max = "x".repeat(MAX_FREE_BYTES);
+        let long = "x".repeat(MAX_FREE_BYTES + 1);
+        let eight_hearts = "💖".repeat(8); // 32 bytes
+        for (input, want) in [
+            ("🐸", Some("🐸")),
+            ("  🐸 ", Some("🐸")),
+            ("any text", Some("any text")),
+            (max.as_str(), Some(max.as_str())),
+            (eight_hearts.as_str(), Some(eight_hearts.as_str())),
+            (long.as_str(), None),
+            ("💖💖💖💖💖💖💖💖💖", None),
+            ("a\u{7}b", None),
+            ("a\nb", None),
+            ("   ", None),
+        ] {
+            assert_eq!(
+                check(SignetMode::Free, input),
+                Ok(want.map(str::to_string)),
+                "{input:?}"
+            );
+        }
+        assert_eq!(
+            SignetPolicy {
+                mode: SignetMode::Free
+            }
+            .check(None),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn grapheme_mode_takes_one_cluster() {
+        let combining = format!("a{}", "\u{301}".repeat(MAX_GRAPHEME_CHARS));
+        for input in ["🐸", "👨‍👩‍👧‍👦", "👩🏽‍💻", "🇷🇴", "a", "Ș", "🛰️", "é"]
+        {
+            assert_eq!(
+                check(SignetMode::Grapheme, input),
+                Ok(Some(input.to_string())),
+                "{input:?}"
+            );
+        }
+        for input in ["🐸🐺", "ab", "🇷🇴🇪🇸", combining.as_str(), "\u{7}"] {
+            assert!(check(SignetMode::Grapheme, input).is_err(), "{input:?}");
+        }
+        assert_eq!(check(SignetMode::Grapheme, " "), Ok(None));
+    }
+
+    #[test]
+    fn registry_mode_takes_the_protocol_set() {
+        for (input, glyph) in [
+            ("🛰️", "🛰️"),
+            ("🛰", "🛰️"),
+            ("🐺", "🐺"),
+            ("pack", "🐺"),
+            ("PACK", "🐺"),
+            (" Song ", "📻"),
+            ("💖", "💖"),
+        ] {
+            assert_eq!(
+                check(SignetMode::Registry, input),
+                Ok(Some(glyph.to_string())),
+                "{input:?}"
+            );
+        }
+        for input in ["📦", "🐸🐺", "wolf", "The Pack", "x"] {
+            assert!(check(SignetMode::Registry, input).is_err(), "{input:?}");
+        }
+        assert!(lookup("⚓\u{FE0F}").is_some());
+        assert!(lookup("").is_none());
+    }
+}
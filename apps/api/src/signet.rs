@@ -0,0 +1,340 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use url::Url;
+
+use crate::registry::{self, SignetMode};
+
+pub const DEFAULT_SIGNET: &str = "💖";
+
+// Shipped rules; signets.rules_path (SIGNET_RULES_PATH) replaces them wholesale.
+const BUILTIN: &str = include_str!("../signets.toml");
+
+#[derive(Deserialize)]
+struct RuleFile {
+    #[serde(default)]
+    rules: Vec<Rule>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Rule {
+    name: Option<String>,
+    signet: String,
+    #[serde(default)]
+    priority: i32,
+    #[serde(default)]
+    hosts: Vec<String>,
+    #[serde(default)]
+    paths: Vec<String>,
+    #[serde(default)]
+    query: Vec<String>,
+}
+
+impl Rule {
+    fn check(&self, n: usize) -> Result<(), String> {
+        let label = self.name.clone().unwrap_or_else(|| format!("#{}", n + 1));
+        let signet = self.signet.trim();
+        if signet.is_empty() || signet.len() > 32 || signet.chars().any(char::is_control) {
+            return Err(format!("rule {label}: bad signet"));
+        }
+        if self.hosts.is_empty() && self.paths.is_empty() && self.query.is_empty() {
+            return Err(format!("rule {label}: needs hosts, paths or query"));
+        }
+        Ok(())
+    }
+
+    fn matches(&self, host: &str, path: &str, query: &[(String, String)]) -> bool {
+        (self.hosts.is_empty() || self.hosts.iter().any(|h| host_matches(host, h)))
+            && (self.paths.is_empty() || self.paths.iter().any(|p| path_matches(path, p)))
+            && (self.query.is_empty() || self.query.iter().any(|q| query_matches(query, q)))
+    }
+}
+
+fn host_matches(host: &str, rule: &str) -> bool {
+    let rule = rule.trim_start_matches("*.").trim_start_matches("www.");
+    host == rule
+        || host
+            .strip_suffix(rule)
+            .is_some_and(|rest| rest.ends_with('.'))
+}
+
+// "/track" covers /track and /track/123 but not /tracks; "*.pdf" is a suffix.
+fn path_matches(path: &str, rule: &str) -> bool {
+    let rule = rule.to_lowercase();
+    if let Some(suffix) = rule.strip_prefix('*') {
+        return path.ends_with(suffix);
+    }
+    match path.strip_prefix(rule.as_str()) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/') || rule.ends_with('/'),
+        None => false,
+    }
+}
+
+fn query_matches(query: &[(String, String)], rule: &str) -> bool {
+    match rule.split_once('=') {
+        Some((name, value)) => query.iter().any(|(k, v)| k == name && v == value),
+        None => query.iter().any(|(k, _)| k == rule),
+    }
+}
+
+#[derive(Serialize)]
+pub struct Detection {
+    pub signet: String,
+    // Name of the winning rule; None = nothing matched, default signet.
+    pub rule: Option<String>,
+}
+
+pub struct SignetRules {
+    // Sorted by priority, highest first; stable, so file order breaks ties.
+    rules: Vec<Rule>,
+}
+
+impl SignetRules {
+    // No path = the shipped rules. In registry mode detection may only hand
+    // out the Protocol Set too: other rules are skipped, so the next match
+    // (or 💖) wins.
+    pub fn new(path: Option<&Path>, mode: SignetMode) -> anyhow::Result<Self> {
+        let rules = match path {
+            Some(path) => Self::load(path)?,
+            None => Self::parse(BUILTIN, false)
+                .map_err(|err| anyhow::anyhow!("built-in signet rules: {err}"))?,
+        };
+        Ok(match mode {
+            SignetMode::Registry => rules.registered_only(),
+            _ => rules,
+        })
+    }
+
+    fn registered_only(mut self) -> Self {
+        let before = self.rules.len();
+        self.rules
+            .retain_mut(|rule| match registry::lookup(&rule.signet) {
+                Some(signet) => {
+                    rule.signet = signet.glyph.to_string();
+                    true
+                }
+                None => false,
+            });
+        if self.rules.len() < before {
+            tracing::info!(
+                skipped = before - self.rules.len(),
+                "signet rules outside the Protocol Set skipped (SIGNET_MODE=registry)"
+            );
+        }
+        self
+    }
+
+    fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| anyhow::anyhow!("signets.rules_path {}: {err}", path.display()))?;
+        let json = path.extension().is_some_and(|ext| ext == "json");
+        let rules = Self::parse(&text, json)
+            .map_err(|err| anyhow::anyhow!("signets.rules_path {}: {err}", path.display()))?;
+        tracing::info!(rules = rules.rules.len(), path = %path.display(), "signet rules loaded");
+        Ok(rules)
+    }
+
+    fn parse(text: &str, json: bool) -> Result<Self, String> {
+        let file: RuleFile = if json {
+            serde_json::from_str(text).map_err(|err| err.to_string())?
+        } else {
+            toml::from_str(text).map_err(|err| err.to_string())?
+        };
+        let mut rules = file.rules;
+        for (n, rule) in rules.iter_mut().enumerate() {
+            rule.check(n)?;
+            rule.signet = rule.signet.trim().to_string();
+            rule.name.get_or_insert_with(|| format!("#{}", n + 1));
+            for host in rule.hosts.iter_mut() {
+                let lower = host.trim().trim_end_matches('.').to_ascii_lowercase();
+                *host = idna::domain_to_ascii(&lower).unwrap_or(lower);
+            }
+        }
+        rules.sort_by_key(|rule| std::cmp::Reverse(rule.priority));
+        Ok(Self { rules })
+    }
+
+    // Looks at the parsed url only: host, path and query params, never the
+    // raw string ("groups" is not "ups").
+    pub fn detect(&self, url: &str) -> Detection {
+        let miss = Detection {
+            signet: DEFAULT_SIGNET.to_string(),
+            rule: None,
+        };
+        let Ok(parsed) = Url::parse(url) else {
+            return miss;
+        };
+        let Some(host) = parsed.host_str() else {
+            return miss;
+        };
+        let host = host.trim_end_matches('.').trim_start_matches("www.");
+        let path = parsed.path().to_lowercase();
+        let query: Vec<(String, String)> = parsed
+            .query_pairs()
+            .map(|(k, v)| (k.into_owned(), v.into_owned()))
+            .collect();
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(host, &path, &query))
+            .map(|rule| Detection {
+                signet: rule.signet.clone(),
+                rule: rule.name.clone(),
+            })
+            .unwrap_or(miss)
+    }
+
+    // Signets detection can hand out (the default included).
+    pub fn knows(&self, signet: &str) -> bool {
+        signet == DEFAULT_SIGNET || self.rules.iter().any(|rule| rule.signet == signet)
+    }
+
+    // A hand-picked signet wins; otherwise the url decides.
+    pub fn resolve(&self, url: Option<&str>, emoji: Option<String>) -> String {
+        if let Some(value) = emoji {
+            if !value.trim().is_empty() {
+                return value;
+            }
+        }
+        match url {
+            Some(link) => self.detect(link).signet,
+            None => DEFAULT_SIGNET.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_rules_read_the_parsed_url() {
+        let rules = SignetRules::new(None, SignetMode::Free).unwrap();
+        for (url, signet, rule) in [
+            (
+                "https://www.dhl.com/ro-en/home/tracking.html",
+                "📦",
+                Some("couriers"),
+            ),
+            ("https://nolp.dhl.de/x", "📦", Some("couriers")),
+            (
+                "https://shop.example/?awb=123",
+                "📦",
+                Some("tracking-number"),
+            ),
+            (
+                "https://shop.example/track/123",
+                "📦",
+                Some("tracking-page"),
+            ),
+            (
+                "https://docs.google.com/document/d/1",
+                "📜",
+                Some("documents"),
+            ),
+            (
+                "https://github.com/a/b/raw/spec.PDF",
+                "📜",
+                Some("document-file"),
+            ),
+            ("https://www.google.com/maps/place/x", "📍", Some("maps")),
+            ("https://maps.app.goo.gl/abc", "📍", Some("places")),
+            ("https://open.spotify.com/track/1", "📻", Some("music")),
+            ("https://docs.rs/url", "🧑‍💻", Some("code")),
+            ("https://example.com/pay/now", "💳", Some("checkout")),
+            // Substrings of a host or path are not matches.
+            ("https://groups.google.com/g/rust", DEFAULT_SIGNET, None),
+            ("https://notdhl.com/", DEFAULT_SIGNET, None),
+            ("https://shop.example/tracks", DEFAULT_SIGNET, None),
+            ("https://www.google.com/search?q=maps", DEFAULT_SIGNET, None),
+            ("not a url", DEFAULT_SIGNET, None),
+        ] {
+            let found = rules.detect(url);
+            assert_eq!(found.signet, signet, "{url}");
+            assert_eq!(found.rule.as_deref(), rule, "{url}");
+        }
+    }
+
+    #[test]
+    fn priority_then_file_order() {
+        let rules = SignetRules::parse(
+            r#"
+            [[rules]]
+            name = "first"
+            signet = "🐺"
+            hosts = ["example.com"]
+
+            [[rules]]
+            name = "second"
+            signet = "🐸"
+            hosts = ["example.com"]
+
+            [[rules]]
+            name = "exact"
+            signet = "🌸"
+            priority = 5
+            query = ["mode=petal"]
+            "#,
+            false,
+        )
+        .unwrap();
+        for (url, rule) in [
+            ("https://example.com/", Some("first")),
+            ("https://example.com/?mode=petal", Some("exact")),
+            ("https://example.com/?mode=other", Some("first")),
+            ("https://other.org/?mode=petal", Some("exact")),
+            ("https://other.org/?mode", None),
+        ] {
+            assert_eq!(rules.detect(url).rule.as_deref(), rule, "{url}");
+        }
+    }
+
+    #[test]
+    fn rejects_rules_that_cannot_match() {
+        for (text, json) in [
+            ("[[rules]]\nsignet = \"🐺\"", false),
+            ("[[rules]]\nsignet = \"  \"\nhosts = [\"a.com\"]", false),
+            ("[[rules]]\nsignet = \"🐺\"\nhost = [\"a.com\"]", false),
+            (r#"{"rules": [{"signet": "🐺"}]}"#, true),
+        ] {
+            assert!(SignetRules::parse(text, json).is_err(), "{text}");
+        }
+        let json = r#"{"rules": [{"signet": "🐺", "hosts": ["Bücher.de"]}]}"#;
+        let rules = SignetRules::parse(json, true).unwrap();
+        assert_eq!(rules.detect("https://bücher.de/").signet, "🐺");
+    }
+
+    #[test]
+    fn registry_mode_detects_only_the_protocol_set() {
+        let rules = SignetRules::new(None, SignetMode::Registry).unwrap();
+        for (url, signet) in [
+            ("https://www.dhl.com/tracking", DEFAULT_SIGNET),
+            ("https://docs.google.com/document/d/1", DEFAULT_SIGNET),
+            ("https://www.google.com/maps/place/x", DEFAULT_SIGNET),
+            ("https://github.com/a/b", DEFAULT_SIGNET),
+            ("https://open.spotify.com/track/1", "📻"),
+        ] {
+            assert_eq!(rules.detect(url).signet, signet, "{url}");
+        }
+        for glyph in ["📦", "📜", "📍", "💳"] {
+            assert!(!rules.knows(glyph), "{glyph}");
+        }
+
+        // A lower rule still gets its turn; keys become glyphs.
+        let rules = SignetRules::parse(
+            r#"
+            [[rules]]
+            signet = "📦"
+            priority = 9
+            hosts = ["example.com"]
+
+            [[rules]]
+            signet = "pack"
+            hosts = ["example.com"]
+            "#,
+            false,
+        )
+        .unwrap()
+        .registered_only();
+        assert_eq!(rules.detect("https://example.com/").signet, "🐺");
+    }
+}
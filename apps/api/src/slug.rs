@@ -13,3 +13,92 @@ pub fn gen_slug(len: usize) -> String {
         })
         .collect()
 }
+
+// Vanity slugs: extended alphabet (a-z, 0-9, '-') since people pick words,
+// not dictation-safe codes. No leading/trailing or doubled dashes.
+pub const VANITY_MIN_LEN: usize = 3;
+pub const VANITY_MAX_LEN: usize = 32;
+
+// Top-level paths owned by the API or the web app (shorter ones like /r
+// and /og are already below VANITY_MIN_LEN), and "stats", which
+// /api/links/:slug/stats would shadow for /api/links/@crew/stats.
+const RESERVED: &[&str] = &[
+    "api",
+    "healthz",
+    "joint",
+    "docs",
+    "covenant",
+    "metrics",
+    "stats",
+    "static",
+    "assets",
+    "admin",
+    "well-known",
+    "favicon",
+    "robots",
+    "sitemap",
+];
+
+pub fn check_vanity(input: &str) -> Result<String, &'static str> {
+    let slug = input.trim().to_ascii_lowercase();
+    if slug.len() < VANITY_MIN_LEN || slug.len() > VANITY_MAX_LEN {
+        return Err("slug must be 3-32 characters\n");
+    }
+    if !slug
+        .bytes()
+        .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+    {
+        return Err("slug may only contain a-z, 0-9 and '-'\n");
+    }
+    if slug.starts_with('-') || slug.ends_with('-') || slug.contains("--") {
+        return Err("slug has misplaced '-'\n");
+    }
+    if RESERVED.contains(&slug.as_str()) {
+        return Err("slug is reserved\n");
+    }
+    Ok(slug)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vanity_rules() {
+        for (input, want) in [
+            ("abc", Ok("abc")),
+            ("  My-Trip-2026 ", Ok("my-trip-2026")),
+            ("a-b", Ok("a-b")),
+            (
+                &"x".repeat(VANITY_MAX_LEN),
+                Ok(&*"x".repeat(VANITY_MAX_LEN)),
+            ),
+            ("ab", Err("slug must be 3-32 characters\n")),
+            (
+                &"x".repeat(VANITY_MAX_LEN + 1),
+                Err("slug must be 3-32 characters\n"),
+            ),
+            ("", Err("slug must be 3-32 characters\n")),
+            ("a_b", Err("slug may only contain a-z, 0-9 and '-'\n")),
+            ("caf\u{e9}", Err("slug may only contain a-z, 0-9 and '-'\n")),
+            ("a b c", Err("slug may only contain a-z, 0-9 and '-'\n")),
+            ("-abc", Err("slug has misplaced '-'\n")),
+            ("abc-", Err("slug has misplaced '-'\n")),
+            ("a--b", Err("slug has misplaced '-'\n")),
+            ("api", Err("slug is reserved\n")),
+            ("Stats", Err("slug is reserved\n")),
+            ("JOINT", Err("slug is reserved\n")),
+            ("well-known", Err("slug is reserved\n")),
+        ] {
+            let got = check_vanity(input);
+            assert_eq!(got.as_deref().map_err(|err| *err), want, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn generated_slugs_skip_lookalikes() {
+        let slug = gen_slug(64);
+        assert_eq!(slug.len(), 64);
+        assert!(!slug.contains(['i', 'l', '1', '0', 'o']), "{slug}");
+    }
+}
@@ -0,0 +1,412 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::{BTreeMap, HashMap};
+use tokio::sync::RwLock;
+
+use super::{
+    Conflict, Journey, LinkRow, LinkStore, LinkUpdate, ListedLink, NewLink, OpenStats, StatsRow,
+    ThreadRow, Watchers, TOMBSTONE_GRACE_SECS,
+};
+
+type Key = (String, String);
+
+struct Link {
+    url: Option<String>,
+    note: Option<String>,
+    created_at: i64,
+    expires_at: Option<i64>,
+    views: i64,
+    max_views: Option<i64>,
+    reply_to: Option<String>,
+    emoji: Option<String>,
+    owner_hash: Option<String>,
+    envelope: Option<String>,
+    password_hash: Option<String>,
+    not_before: Option<i64>,
+    receipt_hash: Option<String>,
+    webhook_url: Option<String>,
+    webhook_secret: Option<String>,
+    blocked: Option<String>,
+    // Insertion order, the rowid tiebreak of the SQL stores.
+    seq: u64,
+}
+
+impl Link {
+    fn alive(&self, now: i64) -> bool {
+        self.expires_at.is_none_or(|at| at > now)
+            && self.max_views.is_none_or(|limit| self.views < limit)
+    }
+
+    fn open(&self, now: i64) -> bool {
+        self.not_before.is_none_or(|at| at <= now)
+    }
+
+    fn row(&self) -> LinkRow {
+        LinkRow {
+            url: self.url.clone(),
+            note: self.note.clone(),
+            expires_at: self.expires_at,
+            reply_to: self.reply_to.clone(),
+            emoji: self.emoji.clone(),
+            envelope: self.envelope.clone(),
+            password_hash: self.password_hash.clone(),
+            views: self.views,
+            max_views: self.max_views,
+            not_before: self.not_before,
+            blocked: self.blocked.clone(),
+        }
+    }
+
+    fn watchers(&self, (callsign, slug): &Key) -> Watchers {
+        Watchers {
+            slug: slug.clone(),
+            callsign: callsign.clone(),
+            receipt_hash: self.receipt_hash.clone(),
+            webhook_url: self.webhook_url.clone(),
+            webhook_secret: self.webhook_secret.clone(),
+        }
+    }
+
+    fn thread(&self, (callsign, slug): &Key) -> ThreadRow {
+        ThreadRow {
+            slug: slug.clone(),
+            callsign: callsign.clone(),
+            reply_to: self.reply_to.clone(),
+            emoji: self.emoji.clone(),
+            has_url: self.url.is_some(),
+            locked: self.password_hash.is_some(),
+            created_at: self.created_at,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Inner {
+    links: BTreeMap<Key, Link>,
+    // Open timestamps per path.
+    events: HashMap<Key, Vec<i64>>,
+    tombstones: HashMap<Key, StatsRow>,
+    callsigns: HashMap<String, String>,
+    seq: u64,
+}
+
+impl Inner {
+    fn clear_stats(&mut self, key: &Key) {
+        self.events.remove(key);
+        self.tombstones.remove(key);
+    }
+}
+
+// Lives and dies with the process: tests, demos, throwaway instances.
+#[derive(Default)]
+pub struct MemoryStore {
+    inner: RwLock<Inner>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    async fn fetch(
+        &self,
+        ns: &str,
+        slug: &str,
+        now: i64,
+        with_pending: bool,
+    ) -> Result<Option<LinkRow>> {
+        let inner = self.inner.read().await;
+        Ok(inner
+            .links
+            .get(&key(ns, slug))
+            .filter(|link| link.alive(now) && (with_pending || link.open(now)))
+            .map(Link::row))
+    }
+}
+
+fn key(ns: &str, slug: &str) -> Key {
+    (ns.to_string(), slug.to_string())
+}
+
+#[async_trait]
+impl LinkStore for MemoryStore {
+    async fn insert(&self, link: &NewLink<'_>) -> Result<()> {
+        let mut inner = self.inner.write().await;
+        let key = key(link.callsign, link.slug);
+        if inner.links.contains_key(&key) {
+            return Err(Conflict.into());
+        }
+        inner.seq += 1;
+        let seq = inner.seq;
+        let owned = |value: Option<&str>| value.map(str::to_string);
+        inner.links.insert(
+            key.clone(),
+            Link {
+                url: owned(link.url),
+                note: owned(link.note),
+                created_at: link.created_at,
+                expires_at: link.expires_at,
+                views: 0,
+                max_views: link.max_views,
+                reply_to: owned(link.reply_to),
+                emoji: owned(link.emoji),
+                owner_hash: owned(link.owner_hash),
+                envelope: owned(link.envelope),
+                password_hash: owned(link.password_hash),
+                not_before: link.not_before,
+                receipt_hash: owned(link.receipt_hash),
+                webhook_url: owned(link.webhook_url),
+                webhook_secret: owned(link.webhook_secret),
+                blocked: None,
+                seq,
+            },
+        );
+        inner.clear_stats(&key);
+        Ok(())
+    }
+
+    async fn get(&self, ns: &str, slug: &str, now: i64) -> Result<Option<LinkRow>> {
+        self.fetch(ns, slug, now, false).await
+    }
+
+    async fn get_with_pending(&self, ns: &str, slug: &str, now: i64) -> Result<Option<LinkRow>> {
+        self.fetch(ns, slug, now, true).await
+    }
+
+    async fn commence(&self, ns: &str, slug: &str, now: i64) -> Result<Option<Journey>> {
+        let mut inner = self.inner.write().await;
+        let key = key(ns, slug);
+        let Some(link) = inner
+            .links
+            .get_mut(&key)
+            .filter(|link| link.alive(now) && link.open(now))
+        else {
+            return Ok(None);
+        };
+        link.views += 1;
+        let views = link.views;
+        let max_views = link.max_views;
+        let watchers = link.watchers(&key);
+        let consumed = max_views.is_some_and(|limit| views >= limit);
+        inner.events.entry(key.clone()).or_default().push(now);
+        if consumed {
+            if let Some(link) = inner.links.remove(&key) {
+                inner.tombstones.insert(
+                    key,
+                    StatsRow {
+                        owner_hash: link.owner_hash,
+                        created_at: link.created_at,
+                        expires_at: link.expires_at,
+                        not_before: None,
+                        views,
+                        max_views,
+                        consumed_at: Some(now),
+                    },
+                );
+            }
+        }
+        Ok(Some(Journey {
+            views,
+            max_views,
+            consumed,
+            watchers,
+        }))
+    }
+
+    async fn burn(&self, ns: &str, slug: &str) -> Result<Option<Watchers>> {
+        let mut inner = self.inner.write().await;
+        let key = key(ns, slug);
+        let Some(link) = inner.links.remove(&key) else {
+            return Ok(None);
+        };
+        inner.clear_stats(&key);
+        Ok(Some(link.watchers(&key)))
+    }
+
+    async fn purge(&self, now: i64, batch: i64) -> Result<Vec<Watchers>> {
+        let mut inner = self.inner.write().await;
+        let due: Vec<Key> = inner
+            .links
+            .iter()
+            .filter(|(_, link)| link.expires_at.is_some_and(|at| at <= now))
+            .map(|(key, _)| key.clone())
+            .take(batch.max(0) as usize)
+            .collect();
+        Ok(due
+            .into_iter()
+            .filter_map(|key| inner.links.remove(&key).map(|link| link.watchers(&key)))
+            .collect())
+    }
+
+    async fn purge_path(&self, ns: &str, slug: &str, now: i64) -> Result<Option<Watchers>> {
+        let mut inner = self.inner.write().await;
+        let key = key(ns, slug);
+        let expired = inner
+            .links
+            .get(&key)
+            .is_some_and(|link| link.expires_at.is_some_and(|at| at <= now));
+        if !expired {
+            return Ok(None);
+        }
+        Ok(inner.links.remove(&key).map(|link| link.watchers(&key)))
+    }
+
+    async fn list(&self, after: (&str, &str), limit: i64) -> Result<Vec<ListedLink>> {
+        use std::ops::Bound::{Excluded, Unbounded};
+        let inner = self.inner.read().await;
+        Ok(inner
+            .links
+            .range((Excluded(key(after.0, after.1)), Unbounded))
+            .filter_map(|((callsign, slug), link)| {
+                Some(ListedLink {
+                    callsign: callsign.clone(),
+                    slug: slug.clone(),
+                    url: link.url.clone()?,
+                    blocked: link.blocked.clone(),
+                })
+            })
+            .take(limit.max(0) as usize)
+            .collect())
+    }
+
+    async fn update(
+        &self,
+        ns: &str,
+        slug: &str,
+        update: &LinkUpdate<'_>,
+        now: i64,
+    ) -> Result<bool> {
+        let mut inner = self.inner.write().await;
+        let Some(link) = inner
+            .links
+            .get_mut(&key(ns, slug))
+            .filter(|link| link.alive(now))
+        else {
+            return Ok(false);
+        };
+        link.url = update.url.map(str::to_string);
+        link.note = update.note.map(str::to_string);
+        link.emoji = update.emoji.map(str::to_string);
+        link.expires_at = update.expires_at;
+        link.max_views = update.max_views;
+        link.blocked = update.blocked.map(str::to_string);
+        Ok(true)
+    }
+
+    async fn set_blocked(&self, ns: &str, slug: &str, blocked: Option<&str>) -> Result<()> {
+        if let Some(link) = self.inner.write().await.links.get_mut(&key(ns, slug)) {
+            link.blocked = blocked.map(str::to_string);
+        }
+        Ok(())
+    }
+
+    async fn owner_hash(&self, ns: &str, slug: &str) -> Result<Option<Option<String>>> {
+        let inner = self.inner.read().await;
+        Ok(inner
+            .links
+            .get(&key(ns, slug))
+            .map(|link| link.owner_hash.clone()))
+    }
+
+    async fn thread_node(&self, ns: &str, slug: &str, now: i64) -> Result<Option<ThreadRow>> {
+        let inner = self.inner.read().await;
+        let key = key(ns, slug);
+        Ok(inner
+            .links
+            .get(&key)
+            .filter(|link| link.alive(now) && link.open(now))
+            .map(|link| link.thread(&key)))
+    }
+
+    async fn thread_replies(&self, parent: &str, now: i64) -> Result<Vec<ThreadRow>> {
+        let inner = self.inner.read().await;
+        let mut replies: Vec<(&Key, &Link)> = inner
+            .links
+            .iter()
+            .filter(|(_, link)| {
+                link.reply_to.as_deref() == Some(parent) && link.alive(now) && link.open(now)
+            })
+            .collect();
+        replies.sort_by_key(|(_, link)| (link.created_at, link.seq));
+        Ok(replies
+            .into_iter()
+            .map(|(key, link)| link.thread(key))
+            .collect())
+    }
+
+    async fn receipt_exists(&self, receipt_hash: &str) -> Result<bool> {
+        let inner = self.inner.read().await;
+        Ok(inner
+            .links
+            .values()
+            .any(|link| link.receipt_hash.as_deref() == Some(receipt_hash)))
+    }
+
+    async fn stats_row(&self, ns: &str, slug: &str) -> Result<Option<StatsRow>> {
+        let inner = self.inner.read().await;
+        let key = key(ns, slug);
+        if let Some(link) = inner.links.get(&key) {
+            return Ok(Some(StatsRow {
+                owner_hash: link.owner_hash.clone(),
+                created_at: link.created_at,
+                expires_at: link.expires_at,
+                not_before: link.not_before,
+                views: link.views,
+                max_views: link.max_views,
+                consumed_at: None,
+            }));
+        }
+        Ok(inner.tombstones.get(&key).cloned())
+    }
+
+    async fn open_stats(&self, ns: &str, slug: &str) -> Result<OpenStats> {
+        let inner = self.inner.read().await;
+        let opens = inner
+            .events
+            .get(&key(ns, slug))
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let mut per_day: BTreeMap<i64, i64> = BTreeMap::new();
+        for ts in opens {
+            *per_day.entry(ts / 86400).or_default() += 1;
+        }
+        Ok(OpenStats {
+            first: opens.iter().min().copied(),
+            last: opens.iter().max().copied(),
+            per_day: per_day.into_iter().collect(),
+        })
+    }
+
+    async fn purge_stats(&self, now: i64) -> Result<()> {
+        let mut inner = self.inner.write().await;
+        inner.tombstones.retain(|_, stone| {
+            let until = stone
+                .expires_at
+                .unwrap_or(stone.consumed_at.unwrap_or(0) + TOMBSTONE_GRACE_SECS);
+            until > now
+        });
+        let Inner {
+            links,
+            events,
+            tombstones,
+            ..
+        } = &mut *inner;
+        events.retain(|key, _| links.contains_key(key) || tombstones.contains_key(key));
+        Ok(())
+    }
+
+    async fn claim_callsign(&self, name: &str, owner_hash: &str, _created_at: i64) -> Result<()> {
+        let mut inner = self.inner.write().await;
+        if inner.callsigns.contains_key(name) {
+            return Err(Conflict.into());
+        }
+        inner
+            .callsigns
+            .insert(name.to_string(), owner_hash.to_string());
+        Ok(())
+    }
+
+    async fn callsign_owner(&self, name: &str) -> Result<Option<String>> {
+        Ok(self.inner.read().await.callsigns.get(name).cloned())
+    }
+}
@@ -0,0 +1,227 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use sqlx::SqlitePool;
+use std::sync::Arc;
+
+use crate::{config::DatabaseConfig, migrate::Report};
+
+mod memory;
+mod postgres;
+mod sqlite;
+#[cfg(test)]
+mod tests;
+
+pub use memory::MemoryStore;
+pub use postgres::PgStore;
+pub use sqlite::SqliteStore;
+
+// Everything about links that has to agree across instances: the rows,
+// their opens / tombstones, and callsigns. Attachments and the webhook
+// queue stay in the local SQLite file (db.rs); grants, unlock tokens,
+// receipts and rate buckets in memory. See DatabaseConfig::link_store.
+#[async_trait]
+pub trait LinkStore: Send + Sync {
+    // Brings a store with its own database up to date (dry run: rolled
+    // back). None = nothing of its own: memory, or the SQLite file that
+    // migrate::run already covers.
+    async fn migrate(&self, _dry_run: bool, _now: i64) -> Result<Option<Report>> {
+        Ok(None)
+    }
+
+    // Conflict (see is_conflict) when the path is taken.
+    async fn insert(&self, link: &NewLink<'_>) -> Result<()>;
+    async fn get(&self, ns: &str, slug: &str, now: i64) -> Result<Option<LinkRow>>;
+    // Same as get, but also returns scheduled links that have not opened
+    // yet (peek countdown, distinct "not yet" status, owner edits).
+    async fn get_with_pending(&self, ns: &str, slug: &str, now: i64) -> Result<Option<LinkRow>>;
+    // Proof of Breath: spends one view, atomically.
+    async fn commence(&self, ns: &str, slug: &str, now: i64) -> Result<Option<Journey>>;
+    // Absolute burn: hard delete regardless of url/max_views. None = no such link.
+    async fn burn(&self, ns: &str, slug: &str) -> Result<Option<Watchers>>;
+    // Sweeper: deletes up to `batch` rows whose time is up.
+    async fn purge(&self, now: i64, batch: i64) -> Result<Vec<Watchers>>;
+    // The sweeper for one path: None = no row there, or one still alive.
+    async fn purge_path(&self, ns: &str, slug: &str, now: i64) -> Result<Option<Watchers>>;
+    // Links with a destination, ordered by path, strictly after `after`
+    // (callsign, slug). ("", "") starts from the top.
+    async fn list(&self, after: (&str, &str), limit: i64) -> Result<Vec<ListedLink>>;
+
+    async fn update(&self, ns: &str, slug: &str, update: &LinkUpdate<'_>, now: i64)
+        -> Result<bool>;
+    async fn set_blocked(&self, ns: &str, slug: &str, blocked: Option<&str>) -> Result<()>;
+    // Outer None = no such link, inner None = legacy link created before
+    // owner tokens existed.
+    async fn owner_hash(&self, ns: &str, slug: &str) -> Result<Option<Option<String>>>;
+    async fn thread_node(&self, ns: &str, slug: &str, now: i64) -> Result<Option<ThreadRow>>;
+    // Live direct replies to `parent` (stored reply_to path), oldest first.
+    async fn thread_replies(&self, parent: &str, now: i64) -> Result<Vec<ThreadRow>>;
+    // Does any live link still report to this receipt?
+    async fn receipt_exists(&self, receipt_hash: &str) -> Result<bool>;
+
+    async fn stats_row(&self, ns: &str, slug: &str) -> Result<Option<StatsRow>>;
+    async fn open_stats(&self, ns: &str, slug: &str) -> Result<OpenStats>;
+    // Tombstones past their link's lifetime (a week after the last view
+    // when it had none), then events nobody can ask about any more.
+    async fn purge_stats(&self, now: i64) -> Result<()>;
+
+    async fn claim_callsign(&self, name: &str, owner_hash: &str, created_at: i64) -> Result<()>;
+    async fn callsign_owner(&self, name: &str) -> Result<Option<String>>;
+}
+
+// unset = the SQLite pool everything else lives in; "memory" = this process
+// only; postgres://… = shared between instances.
+pub async fn open(cfg: &DatabaseConfig, pool: &SqlitePool) -> Result<Arc<dyn LinkStore>> {
+    let store: Arc<dyn LinkStore> = match cfg.link_store.as_deref() {
+        None => Arc::new(SqliteStore::new(pool.clone())),
+        Some("memory") => Arc::new(MemoryStore::new()),
+        Some(url) => Arc::new(PgStore::connect(url, cfg.max_connections).await?),
+    };
+    Ok(store)
+}
+
+// Path (primary key) already taken. Backends without a database error to
+// report return this one.
+#[derive(Debug)]
+pub struct Conflict;
+
+impl std::fmt::Display for Conflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("already taken")
+    }
+}
+
+impl std::error::Error for Conflict {}
+
+pub fn is_conflict(err: &anyhow::Error) -> bool {
+    err.is::<Conflict>()
+        || err
+            .downcast_ref::<sqlx::Error>()
+            .and_then(|err| err.as_database_error())
+            .is_some_and(|err| err.is_unique_violation())
+}
+
+pub struct NewLink<'a> {
+    pub slug: &'a str,
+    // '' = the commons (@garden).
+    pub callsign: &'a str,
+    pub url: Option<&'a str>,
+    pub note: Option<&'a str>,
+    pub created_at: i64,
+    pub expires_at: Option<i64>,
+    pub max_views: Option<i64>,
+    pub reply_to: Option<&'a str>,
+    pub emoji: Option<&'a str>,
+    pub owner_hash: Option<&'a str>,
+    // Client-side encrypted note, stored as the opaque JSON envelope.
+    pub envelope: Option<&'a str>,
+    // Argon2 PHC string; NULL = open link.
+    pub password_hash: Option<&'a str>,
+    // Scheduled activation: exists, but resolves to "not yet" until then.
+    pub not_before: Option<i64>,
+    // SHA-256 of the receipt id handed to the sender; NULL = no receipts.
+    pub receipt_hash: Option<&'a str>,
+    // Per-link webhook; the secret signs its deliveries.
+    pub webhook_url: Option<&'a str>,
+    pub webhook_secret: Option<&'a str>,
+}
+
+#[derive(sqlx::FromRow, Clone)]
+pub struct LinkRow {
+    pub url: Option<String>,
+    pub note: Option<String>,
+    pub expires_at: Option<i64>,
+    pub reply_to: Option<String>,
+    pub emoji: Option<String>,
+    pub envelope: Option<String>,
+    pub password_hash: Option<String>,
+    pub views: i64,
+    pub max_views: Option<i64>,
+    pub not_before: Option<i64>,
+    // Denylist rule that matched the destination; NULL = clear.
+    pub blocked: Option<String>,
+}
+
+impl LinkRow {
+    // Some(opens_at) while a scheduled link is still closed.
+    pub fn pending(&self, now: i64) -> Option<i64> {
+        self.not_before.filter(|opens_at| *opens_at > now)
+    }
+}
+
+// Who hears about a link's lifecycle: the sender's receipt stream and
+// its webhook, if it asked for them.
+#[derive(sqlx::FromRow, Clone)]
+pub struct Watchers {
+    pub slug: String,
+    pub callsign: String,
+    pub receipt_hash: Option<String>,
+    pub webhook_url: Option<String>,
+    pub webhook_secret: Option<String>,
+}
+
+const WATCHERS: &str = "slug, callsign, receipt_hash, webhook_url, webhook_secret";
+
+// A view that went through. `consumed` = that was the last one and the
+// row is gone.
+pub struct Journey {
+    pub views: i64,
+    pub max_views: Option<i64>,
+    pub consumed: bool,
+    pub watchers: Watchers,
+}
+
+// Thread view of a link: metadata only, never url / note, so walking a
+// thread can't read (or spend) anyone's petal.
+#[derive(sqlx::FromRow)]
+pub struct ThreadRow {
+    pub slug: String,
+    pub callsign: String,
+    pub reply_to: Option<String>,
+    pub emoji: Option<String>,
+    pub has_url: bool,
+    pub locked: bool,
+    pub created_at: i64,
+}
+
+const THREAD_COLUMNS: &str = "slug, callsign, reply_to, emoji, url IS NOT NULL AS has_url, \
+     password_hash IS NOT NULL AS locked, created_at";
+
+pub struct LinkUpdate<'a> {
+    pub url: Option<&'a str>,
+    pub note: Option<&'a str>,
+    pub emoji: Option<&'a str>,
+    pub expires_at: Option<i64>,
+    pub max_views: Option<i64>,
+    pub blocked: Option<&'a str>,
+}
+
+// Denylist rescan row.
+#[derive(sqlx::FromRow)]
+pub struct ListedLink {
+    pub callsign: String,
+    pub slug: String,
+    pub url: String,
+    pub blocked: Option<String>,
+}
+
+// Stats source: the live row, or the tombstone a consumed link left.
+#[derive(sqlx::FromRow, Clone)]
+pub struct StatsRow {
+    pub owner_hash: Option<String>,
+    pub created_at: i64,
+    pub expires_at: Option<i64>,
+    pub not_before: Option<i64>,
+    pub views: i64,
+    pub max_views: Option<i64>,
+    pub consumed_at: Option<i64>,
+}
+
+pub struct OpenStats {
+    pub first: Option<i64>,
+    pub last: Option<i64>,
+    // (unix day, opens), oldest first.
+    pub per_day: Vec<(i64, i64)>,
+}
+
+// How long a tombstone without an expiry outlives its last view.
+const TOMBSTONE_GRACE_SECS: i64 = 604800;
@@ -0,0 +1,453 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use sqlx::{FromRow, Row, SqlitePool};
+
+use super::{
+    Journey, LinkRow, LinkStore, LinkUpdate, ListedLink, NewLink, OpenStats, StatsRow, ThreadRow,
+    Watchers, THREAD_COLUMNS, TOMBSTONE_GRACE_SECS, WATCHERS,
+};
+
+// The links tables of the main SQLite file (migrate.rs owns the schema).
+pub struct SqliteStore {
+    pool: SqlitePool,
+}
+
+impl SqliteStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    async fn fetch(
+        &self,
+        ns: &str,
+        slug: &str,
+        now: i64,
+        with_pending: bool,
+    ) -> Result<Option<LinkRow>> {
+        // Atomic expiry check in DB (Toni Capone style: Contract of Truth)
+        // Read-only: No views increment here (moved to commence endpoint)
+        let row = sqlx::query_as::<_, LinkRow>(
+            r#"
+            SELECT url, note, expires_at, reply_to, emoji, envelope, password_hash,
+                   COALESCE(views, 0) AS views, max_views, not_before, blocked
+            FROM links
+            WHERE slug = ?1 AND callsign = ?3
+              AND (expires_at IS NULL OR expires_at > ?2)
+              AND (max_views IS NULL OR views < max_views)
+              AND (?4 OR not_before IS NULL OR not_before <= ?2)
+            "#,
+        )
+        .bind(slug)
+        .bind(now)
+        .bind(ns)
+        .bind(with_pending)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row)
+    }
+}
+
+async fn clear_stats(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    ns: &str,
+    slug: &str,
+) -> Result<()> {
+    sqlx::query("DELETE FROM link_events WHERE callsign = ?1 AND slug = ?2")
+        .bind(ns)
+        .bind(slug)
+        .execute(&mut **tx)
+        .await?;
+    sqlx::query("DELETE FROM link_tombstones WHERE callsign = ?1 AND slug = ?2")
+        .bind(ns)
+        .bind(slug)
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
+
+const LIVE: &str = "(expires_at IS NULL OR expires_at > ?2) \
+     AND (max_views IS NULL OR views < max_views) \
+     AND (not_before IS NULL OR not_before <= ?2)";
+
+#[async_trait]
+impl LinkStore for SqliteStore {
+    async fn insert(&self, link: &NewLink<'_>) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            r#"
+            INSERT INTO links (slug, url, note, created_at, expires_at, max_views, reply_to, emoji, owner_hash, envelope, password_hash, callsign, not_before, receipt_hash, webhook_url, webhook_secret)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)
+            "#,
+        )
+        .bind(link.slug)
+        .bind(link.url)
+        .bind(link.note)
+        .bind(link.created_at)
+        .bind(link.expires_at)
+        .bind(link.max_views)
+        .bind(link.reply_to)
+        .bind(link.emoji)
+        .bind(link.owner_hash)
+        .bind(link.envelope)
+        .bind(link.password_hash)
+        .bind(link.callsign)
+        .bind(link.not_before)
+        .bind(link.receipt_hash)
+        .bind(link.webhook_url)
+        .bind(link.webhook_secret)
+        .execute(&mut *tx)
+        .await?;
+        // A path can be reused once its link is gone; its stats go with it.
+        clear_stats(&mut tx, link.callsign, link.slug).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get(&self, ns: &str, slug: &str, now: i64) -> Result<Option<LinkRow>> {
+        self.fetch(ns, slug, now, false).await
+    }
+
+    async fn get_with_pending(&self, ns: &str, slug: &str, now: i64) -> Result<Option<LinkRow>> {
+        self.fetch(ns, slug, now, true).await
+    }
+
+    async fn commence(&self, ns: &str, slug: &str, now: i64) -> Result<Option<Journey>> {
+        let mut tx = self.pool.begin().await?;
+        let sql = format!(
+            r#"
+            SELECT COALESCE(views, 0) AS views, max_views, owner_hash, created_at, expires_at,
+                   {WATCHERS}
+            FROM links
+            WHERE slug = ?1 AND callsign = ?3
+              AND (expires_at IS NULL OR expires_at > ?2)
+              AND (max_views IS NULL OR views < max_views)
+              AND (not_before IS NULL OR not_before <= ?2)
+            "#
+        );
+        let row = sqlx::query(&sql)
+            .bind(slug)
+            .bind(now)
+            .bind(ns)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let views: i64 = row.try_get("views")?;
+        let max_views: Option<i64> = row.try_get("max_views")?;
+        let watchers = Watchers::from_row(&row)?;
+        let consumed = max_views.is_some_and(|limit| views + 1 >= limit);
+
+        sqlx::query(
+            "INSERT INTO link_events (callsign, slug, event, ts) VALUES (?1, ?2, 'open', ?3)",
+        )
+        .bind(ns)
+        .bind(slug)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+
+        if consumed {
+            let owner_hash: Option<String> = row.try_get("owner_hash")?;
+            let created_at: i64 = row.try_get("created_at")?;
+            let expires_at: Option<i64> = row.try_get("expires_at")?;
+            sqlx::query(
+                r#"
+                INSERT OR REPLACE INTO link_tombstones
+                  (callsign, slug, owner_hash, created_at, expires_at, max_views, views, consumed_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                "#,
+            )
+            .bind(ns)
+            .bind(slug)
+            .bind(owner_hash)
+            .bind(created_at)
+            .bind(expires_at)
+            .bind(max_views)
+            .bind(views + 1)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query("DELETE FROM links WHERE slug = ?1 AND callsign = ?2")
+                .bind(slug)
+                .bind(ns)
+                .execute(&mut *tx)
+                .await?;
+        } else {
+            sqlx::query(
+                r#"
+                UPDATE links
+                SET views = views + 1
+                WHERE slug = ?1 AND callsign = ?2
+                "#,
+            )
+            .bind(slug)
+            .bind(ns)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(Some(Journey {
+            views: views + 1,
+            max_views,
+            consumed,
+            watchers,
+        }))
+    }
+
+    async fn burn(&self, ns: &str, slug: &str) -> Result<Option<Watchers>> {
+        let mut tx = self.pool.begin().await?;
+        let sql =
+            format!("DELETE FROM links WHERE slug = ?1 AND callsign = ?2 RETURNING {WATCHERS}");
+        let row = sqlx::query_as::<_, Watchers>(&sql)
+            .bind(slug)
+            .bind(ns)
+            .fetch_optional(&mut *tx)
+            .await?;
+        // Burned means burned: the stats don't outlive it.
+        if row.is_some() {
+            clear_stats(&mut tx, ns, slug).await?;
+        }
+        tx.commit().await?;
+        Ok(row)
+    }
+
+    // One batch at a time so the write lock is never held for long. Walks
+    // idx_links_expiry.
+    async fn purge(&self, now: i64, batch: i64) -> Result<Vec<Watchers>> {
+        let sql = format!(
+            r#"
+            DELETE FROM links
+            WHERE rowid IN (
+              SELECT rowid FROM links
+              WHERE expires_at IS NOT NULL AND expires_at <= ?1
+              LIMIT ?2
+            )
+            RETURNING {WATCHERS}
+            "#
+        );
+        let rows = sqlx::query_as::<_, Watchers>(&sql)
+            .bind(now)
+            .bind(batch)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows)
+    }
+
+    async fn purge_path(&self, ns: &str, slug: &str, now: i64) -> Result<Option<Watchers>> {
+        let sql = format!(
+            r#"
+            DELETE FROM links
+            WHERE callsign = ?1 AND slug = ?2 AND expires_at IS NOT NULL AND expires_at <= ?3
+            RETURNING {WATCHERS}
+            "#
+        );
+        let row = sqlx::query_as::<_, Watchers>(&sql)
+            .bind(ns)
+            .bind(slug)
+            .bind(now)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row)
+    }
+
+    async fn list(&self, after: (&str, &str), limit: i64) -> Result<Vec<ListedLink>> {
+        let rows = sqlx::query_as::<_, ListedLink>(
+            r#"
+            SELECT callsign, slug, url, blocked FROM links
+            WHERE (callsign, slug) > (?1, ?2) AND url IS NOT NULL
+            ORDER BY callsign, slug
+            LIMIT ?3
+            "#,
+        )
+        .bind(after.0)
+        .bind(after.1)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows)
+    }
+
+    // Owner edit: rewrites the mutable fields, leaves views alone. Only live
+    // links qualify; false = expired or consumed in the meantime.
+    async fn update(
+        &self,
+        ns: &str,
+        slug: &str,
+        update: &LinkUpdate<'_>,
+        now: i64,
+    ) -> Result<bool> {
+        let res = sqlx::query(
+            r#"
+            UPDATE links
+            SET url = ?3, note = ?4, emoji = ?5, expires_at = ?6, max_views = ?7, blocked = ?9
+            WHERE slug = ?1 AND callsign = ?2
+              AND (expires_at IS NULL OR expires_at > ?8)
+              AND (max_views IS NULL OR views < max_views)
+            "#,
+        )
+        .bind(slug)
+        .bind(ns)
+        .bind(update.url)
+        .bind(update.note)
+        .bind(update.emoji)
+        .bind(update.expires_at)
+        .bind(update.max_views)
+        .bind(now)
+        .bind(update.blocked)
+        .execute(&self.pool)
+        .await?;
+        Ok(res.rows_affected() > 0)
+    }
+
+    async fn set_blocked(&self, ns: &str, slug: &str, blocked: Option<&str>) -> Result<()> {
+        sqlx::query("UPDATE links SET blocked = ?3 WHERE callsign = ?1 AND slug = ?2")
+            .bind(ns)
+            .bind(slug)
+            .bind(blocked)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn owner_hash(&self, ns: &str, slug: &str) -> Result<Option<Option<String>>> {
+        let row = sqlx::query_as::<_, (Option<String>,)>(
+            "SELECT owner_hash FROM links WHERE slug = ?1 AND callsign = ?2",
+        )
+        .bind(slug)
+        .bind(ns)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(|(hash,)| hash))
+    }
+
+    async fn thread_node(&self, ns: &str, slug: &str, now: i64) -> Result<Option<ThreadRow>> {
+        let sql = format!(
+            "SELECT {THREAD_COLUMNS} FROM links WHERE slug = ?1 AND callsign = ?3 AND {LIVE}"
+        );
+        let row = sqlx::query_as::<_, ThreadRow>(&sql)
+            .bind(slug)
+            .bind(now)
+            .bind(ns)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row)
+    }
+
+    async fn thread_replies(&self, parent: &str, now: i64) -> Result<Vec<ThreadRow>> {
+        let sql = format!(
+            "SELECT {THREAD_COLUMNS} FROM links WHERE reply_to = ?1 AND {LIVE} ORDER BY created_at, rowid"
+        );
+        let rows = sqlx::query_as::<_, ThreadRow>(&sql)
+            .bind(parent)
+            .bind(now)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows)
+    }
+
+    async fn receipt_exists(&self, receipt_hash: &str) -> Result<bool> {
+        let row =
+            sqlx::query_as::<_, (i64,)>("SELECT 1 FROM links WHERE receipt_hash = ?1 LIMIT 1")
+                .bind(receipt_hash)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(row.is_some())
+    }
+
+    async fn stats_row(&self, ns: &str, slug: &str) -> Result<Option<StatsRow>> {
+        let row = sqlx::query_as::<_, StatsRow>(
+            r#"
+            SELECT owner_hash, created_at, expires_at, not_before, COALESCE(views, 0) AS views,
+                   max_views, NULL AS consumed_at
+            FROM links WHERE callsign = ?1 AND slug = ?2
+            UNION ALL
+            SELECT owner_hash, created_at, expires_at, NULL, views, max_views, consumed_at
+            FROM link_tombstones WHERE callsign = ?1 AND slug = ?2
+            LIMIT 1
+            "#,
+        )
+        .bind(ns)
+        .bind(slug)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row)
+    }
+
+    async fn open_stats(&self, ns: &str, slug: &str) -> Result<OpenStats> {
+        let (first, last) = sqlx::query_as::<_, (Option<i64>, Option<i64>)>(
+            r#"
+            SELECT MIN(ts), MAX(ts) FROM link_events
+            WHERE callsign = ?1 AND slug = ?2 AND event = 'open'
+            "#,
+        )
+        .bind(ns)
+        .bind(slug)
+        .fetch_one(&self.pool)
+        .await?;
+        let per_day = sqlx::query_as::<_, (i64, i64)>(
+            r#"
+            SELECT ts / 86400 AS day, COUNT(*) FROM link_events
+            WHERE callsign = ?1 AND slug = ?2 AND event = 'open'
+            GROUP BY day ORDER BY day
+            "#,
+        )
+        .bind(ns)
+        .bind(slug)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(OpenStats {
+            first,
+            last,
+            per_day,
+        })
+    }
+
+    async fn purge_stats(&self, now: i64) -> Result<()> {
+        sqlx::query(
+            "DELETE FROM link_tombstones WHERE COALESCE(expires_at, consumed_at + ?2) <= ?1",
+        )
+        .bind(now)
+        .bind(TOMBSTONE_GRACE_SECS)
+        .execute(&self.pool)
+        .await?;
+        sqlx::query(
+            r#"
+            DELETE FROM link_events
+            WHERE NOT EXISTS (
+                SELECT 1 FROM links
+                WHERE links.callsign = link_events.callsign AND links.slug = link_events.slug
+              )
+              AND NOT EXISTS (
+                SELECT 1 FROM link_tombstones t
+                WHERE t.callsign = link_events.callsign AND t.slug = link_events.slug
+              )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn claim_callsign(&self, name: &str, owner_hash: &str, created_at: i64) -> Result<()> {
+        sqlx::query("INSERT INTO callsigns (name, owner_hash, created_at) VALUES (?1, ?2, ?3)")
+            .bind(name)
+            .bind(owner_hash)
+            .bind(created_at)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn callsign_owner(&self, name: &str) -> Result<Option<String>> {
+        let row =
+            sqlx::query_as::<_, (String,)>("SELECT owner_hash FROM callsigns WHERE name = ?1")
+                .bind(name)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(row.map(|(hash,)| hash))
+    }
+}
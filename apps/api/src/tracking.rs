@@ -0,0 +1,270 @@
+use std::path::Path;
+use url::Url;
+
+use crate::config::TrackingConfig;
+
+// Query params that only exist to follow people around. A trailing '*'
+// matches by prefix. "fara priviri straine."
+const EVERYWHERE: &[&str] = &[
+    "utm_*",
+    "fbclid",
+    "gclid",
+    "gclsrc",
+    "dclid",
+    "gbraid",
+    "wbraid",
+    "msclkid",
+    "yclid",
+    "twclid",
+    "ttclid",
+    "li_fat_id",
+    "igshid",
+    "igsh",
+    "mc_cid",
+    "mc_eid",
+    "_hsenc",
+    "_hsmi",
+    "mkt_tok",
+    "oly_anon_id",
+    "oly_enc_id",
+    "vero_id",
+    "rb_clickid",
+    "s_cid",
+    "_ga",
+    "_gl",
+];
+
+// Params that are tracking on these hosts (and their subdomains) but may
+// mean something real elsewhere.
+const PER_DOMAIN: &[(&str, &[&str])] = &[
+    ("spotify.com", &["si", "context", "nd"]),
+    ("youtube.com", &["si", "feature", "pp"]),
+    ("youtu.be", &["si", "feature"]),
+    ("twitter.com", &["s", "t", "ref_src", "ref_url"]),
+    ("x.com", &["s", "t", "ref_src", "ref_url"]),
+    (
+        "tiktok.com",
+        &["_r", "_t", "is_from_webapp", "sender_device", "is_copy_url"],
+    ),
+    ("linkedin.com", &["trk", "trackingId", "lipi", "rcm"]),
+    ("facebook.com", &["mibextid", "sfnsn", "rdid"]),
+    ("amazon.com", &["ref", "ref_", "pd_rd_*", "pf_rd_*"]),
+    ("reddit.com", &["share_id", "utm_name", "rdt"]),
+];
+
+struct Rule {
+    // None = every host.
+    domain: Option<String>,
+    param: String,
+}
+
+impl Rule {
+    fn applies(&self, host: &str, param: &str) -> bool {
+        let host_ok = match self.domain.as_deref() {
+            None => true,
+            Some(domain) => host == domain || host.ends_with(&format!(".{domain}")),
+        };
+        host_ok
+            && match self.param.strip_suffix('*') {
+                Some(prefix) => param.starts_with(prefix),
+                None => param == self.param,
+            }
+    }
+}
+
+pub struct TrackingFilter {
+    // Off = pass every url through untouched (tracking.strip = false).
+    enabled: bool,
+    rules: Vec<Rule>,
+}
+
+impl TrackingFilter {
+    // Built-in rules plus tracking.rules_path, one "<domain|*> param param*"
+    // per line ('#' lines are comments).
+    pub fn new(cfg: &TrackingConfig) -> anyhow::Result<Self> {
+        let mut rules: Vec<Rule> = EVERYWHERE
+            .iter()
+            .map(|param| Rule {
+                domain: None,
+                param: param.to_string(),
+            })
+            .chain(PER_DOMAIN.iter().flat_map(|(domain, params)| {
+                params.iter().map(|param| Rule {
+                    domain: Some(domain.to_string()),
+                    param: param.to_string(),
+                })
+            }))
+            .collect();
+        if let Some(path) = cfg.rules_path.as_deref() {
+            rules.extend(load_rules(path)?);
+        }
+        Ok(Self {
+            enabled: cfg.strip,
+            rules,
+        })
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    // Drops tracking params from a normalized url. Returns the cleaned url
+    // and the names that went; untouched params keep their exact encoding.
+    pub fn strip(&self, url: &str) -> (String, Vec<String>) {
+        let Ok(mut parsed) = Url::parse(url) else {
+            return (url.to_string(), Vec::new());
+        };
+        let (Some(host), Some(query)) = (parsed.host_str(), parsed.query()) else {
+            return (url.to_string(), Vec::new());
+        };
+        let host = host.trim_start_matches("www.").to_string();
+
+        let mut kept = Vec::new();
+        let mut removed: Vec<String> = Vec::new();
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let raw_name = pair.split('=').next().unwrap_or(pair);
+            let name = decode_name(raw_name);
+            if self.rules.iter().any(|rule| rule.applies(&host, &name)) {
+                if !removed.contains(&name) {
+                    removed.push(name);
+                }
+            } else {
+                kept.push(pair);
+            }
+        }
+        if removed.is_empty() {
+            return (url.to_string(), removed);
+        }
+        let query = kept.join("&");
+        parsed.set_query((!query.is_empty()).then_some(query.as_str()));
+        (parsed.into(), removed)
+    }
+}
+
+fn decode_name(raw: &str) -> String {
+    url::form_urlencoded::parse(raw.as_bytes())
+        .next()
+        .map(|(name, _)| name.into_owned())
+        .unwrap_or_else(|| raw.to_string())
+}
+
+fn load_rules(path: &Path) -> anyhow::Result<Vec<Rule>> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| anyhow::anyhow!("tracking.rules_path {}: {err}", path.display()))?;
+    let mut rules = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut words = line.split_whitespace();
+        let scope = words.next().unwrap_or_default();
+        let domain = (scope != "*").then(|| scope.trim_start_matches("www.").to_ascii_lowercase());
+        let params: Vec<&str> = words.collect();
+        if params.is_empty() {
+            anyhow::bail!(
+                "tracking.rules_path {} line {}: no params after {scope}",
+                path.display(),
+                n + 1
+            );
+        }
+        rules.extend(params.into_iter().map(|param| Rule {
+            domain: domain.clone(),
+            param: param.to_string(),
+        }));
+    }
+    Ok(rules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_tracking_and_keeps_the_rest() {
+        let filter = TrackingFilter::new(&TrackingConfig::default()).unwrap();
+        for (url, want, removed) in [
+            (
+                "https://example.com/a?utm_source=x&utm_medium=y&id=7",
+                "https://example.com/a?id=7",
+                &["utm_source", "utm_medium"][..],
+            ),
+            (
+                "https://example.com/?fbclid=abc",
+                "https://example.com/",
+                &["fbclid"],
+            ),
+            (
+                "https://shop.example/?gclid=1&q=a%20b&gclid=2",
+                "https://shop.example/?q=a%20b",
+                &["gclid"],
+            ),
+            (
+                "https://open.spotify.com/track/1?si=abc",
+                "https://open.spotify.com/track/1",
+                &["si"],
+            ),
+            (
+                "https://www.youtube.com/watch?v=dQw&si=x&feature=share",
+                "https://www.youtube.com/watch?v=dQw",
+                &["si", "feature"],
+            ),
+            (
+                "https://example.com/?utm%5Fsource=x&ok=1",
+                "https://example.com/?ok=1",
+                &["utm_source"],
+            ),
+            (
+                "https://example.com/p?a=1#utm_source=frag",
+                "https://example.com/p?a=1#utm_source=frag",
+                &[],
+            ),
+            // si / t are only tracking on their own hosts.
+            (
+                "https://example.com/?si=1&t=30",
+                "https://example.com/?si=1&t=30",
+                &[],
+            ),
+            (
+                "https://notspotify.com/?si=1",
+                "https://notspotify.com/?si=1",
+                &[],
+            ),
+            ("https://example.com/", "https://example.com/", &[]),
+        ] {
+            let (got, gone) = filter.strip(url);
+            assert_eq!(got, want, "{url}");
+            assert_eq!(gone, removed, "{url}");
+        }
+    }
+
+    #[test]
+    fn off_is_off_and_rule_files_add_rules() {
+        let path =
+            std::env::temp_dir().join(format!("amigo-tracking-{}.txt", crate::token::gen_token()));
+        std::fs::write(&path, "# ours\n* ref\nwww.example.org  src  cmp_*\n").unwrap();
+        let filter = TrackingFilter::new(&TrackingConfig {
+            strip: false,
+            rules_path: Some(path.clone()),
+        })
+        .unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert!(!filter.enabled());
+
+        let (got, gone) = filter.strip("https://sub.example.org/?src=a&cmp_id=1&ref=x&keep=1");
+        assert_eq!(got, "https://sub.example.org/?keep=1");
+        assert_eq!(gone, ["src", "cmp_id", "ref"]);
+        let (got, _) = filter.strip("https://example.net/?src=a");
+        assert_eq!(got, "https://example.net/?src=a");
+    }
+
+    #[test]
+    fn rule_lines_need_params() {
+        let path =
+            std::env::temp_dir().join(format!("amigo-tracking-{}.txt", crate::token::gen_token()));
+        std::fs::write(&path, "example.org\n").unwrap();
+        let err = load_rules(&path).err().unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert!(err.to_string().contains("line 1"), "{err}");
+    }
+}
@@ -0,0 +1,193 @@
+// Duration grammar for ttl / opens_in:
+//   compound:  "90s", "30m", "24h", "7d", "1w", "1d12h", "1w2d3h"
+//   ISO 8601:  "P1W", "P2D", "PT90M", "P1DT12H30M"
+// Calendar units (years / months) are rejected: their length depends on
+// the date and a short link doesn't need them.
+
+const MINUTE: i64 = 60;
+const HOUR: i64 = 60 * MINUTE;
+const DAY: i64 = 24 * HOUR;
+const WEEK: i64 = 7 * DAY;
+
+pub fn parse_duration(input: &str) -> Result<i64, String> {
+    let raw = input.trim();
+    if raw.is_empty() {
+        return Err("empty duration".to_string());
+    }
+    if raw.starts_with('-') {
+        return Err(format!("duration must be positive: {raw}"));
+    }
+    let secs = if raw.starts_with(['P', 'p']) {
+        parse_iso(&raw[1..])?
+    } else {
+        parse_compound(raw)?
+    };
+    if secs <= 0 {
+        return Err(format!("duration must be positive: {raw}"));
+    }
+    Ok(secs)
+}
+
+fn unit_secs(unit: char) -> Option<i64> {
+    match unit {
+        's' => Some(1),
+        'm' => Some(MINUTE),
+        'h' => Some(HOUR),
+        'd' => Some(DAY),
+        'w' => Some(WEEK),
+        _ => None,
+    }
+}
+
+fn parse_compound(raw: &str) -> Result<i64, String> {
+    let lower = raw.to_ascii_lowercase();
+    let mut total: i64 = 0;
+    let mut seen = String::new();
+    let mut num = String::new();
+
+    for c in lower.chars() {
+        if c.is_ascii_digit() {
+            num.push(c);
+            continue;
+        }
+        let Some(secs) = unit_secs(c) else {
+            return Err(format!("unknown unit '{c}' in {raw} (use s, m, h, d, w)"));
+        };
+        if num.is_empty() {
+            return Err(format!("missing number before '{c}' in {raw}"));
+        }
+        if seen.contains(c) {
+            return Err(format!("unit '{c}' repeated in {raw}"));
+        }
+        seen.push(c);
+        total = add_part(total, &num, secs, raw)?;
+        num.clear();
+    }
+
+    if !num.is_empty() {
+        return Err(format!(
+            "missing unit after {num} in {raw} (use s, m, h, d, w)"
+        ));
+    }
+    Ok(total)
+}
+
+fn parse_iso(body: &str) -> Result<i64, String> {
+    let raw = format!("P{body}");
+    let upper = body.to_ascii_uppercase();
+    let (date, time) = match upper.split_once('T') {
+        Some((date, time)) if !time.is_empty() => (date.to_string(), Some(time.to_string())),
+        Some(_) => return Err(format!("empty time part in {raw}")),
+        None => (upper, None),
+    };
+    if date.is_empty() && time.is_none() {
+        return Err(format!("empty ISO 8601 duration {raw}"));
+    }
+
+    let mut total = 0;
+    let mut num = String::new();
+    for c in date.chars() {
+        match c {
+            '0'..='9' => num.push(c),
+            'W' | 'D' if !num.is_empty() => {
+                let secs = if c == 'W' { WEEK } else { DAY };
+                total = add_part(total, &num, secs, &raw)?;
+                num.clear();
+            }
+            'Y' | 'M' => return Err(format!("calendar units (Y, M) not supported in {raw}")),
+            _ => return Err(format!("invalid ISO 8601 duration {raw}")),
+        }
+    }
+    if !num.is_empty() {
+        return Err(format!("invalid ISO 8601 duration {raw}"));
+    }
+
+    for c in time.unwrap_or_default().chars() {
+        match c {
+            '0'..='9' => num.push(c),
+            'H' | 'M' | 'S' if !num.is_empty() => {
+                let secs = match c {
+                    'H' => HOUR,
+                    'M' => MINUTE,
+                    _ => 1,
+                };
+                total = add_part(total, &num, secs, &raw)?;
+                num.clear();
+            }
+            _ => return Err(format!("invalid ISO 8601 duration {raw}")),
+        }
+    }
+    if !num.is_empty() {
+        return Err(format!("invalid ISO 8601 duration {raw}"));
+    }
+    Ok(total)
+}
+
+fn add_part(total: i64, num: &str, unit: i64, raw: &str) -> Result<i64, String> {
+    num.parse::<i64>()
+        .ok()
+        .and_then(|n| n.checked_mul(unit))
+        .and_then(|secs| total.checked_add(secs))
+        .ok_or_else(|| format!("duration too large: {raw}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_compound_and_iso() {
+        for (input, secs) in [
+            ("90s", 90),
+            ("30m", 30 * MINUTE),
+            ("24h", DAY),
+            ("7d", WEEK),
+            ("1w", WEEK),
+            ("1d12h", DAY + 12 * HOUR),
+            ("1w2d3h", WEEK + 2 * DAY + 3 * HOUR),
+            ("1H30M", HOUR + 30 * MINUTE),
+            (" 2d ", 2 * DAY),
+            ("P1W", WEEK),
+            ("P2D", 2 * DAY),
+            ("PT90M", 90 * MINUTE),
+            ("P1DT12H30M", DAY + 12 * HOUR + 30 * MINUTE),
+            ("pt45s", 45),
+        ] {
+            assert_eq!(parse_duration(input), Ok(secs), "{input}");
+        }
+    }
+
+    #[test]
+    fn rejects_the_rest() {
+        for input in [
+            "",
+            "   ",
+            "7",
+            "d",
+            "-1d",
+            "0s",
+            "1y",
+            "1d1d",
+            "1x",
+            "P",
+            "PT",
+            "P1Y",
+            "P1M",
+            "P1Y2M",
+            "P1H",
+            "PT1D",
+            "P1DT",
+            "P1.5D",
+            "99999999999999999999s",
+            "999999999999999w",
+        ] {
+            assert!(parse_duration(input).is_err(), "{input:?} accepted");
+        }
+    }
+
+    #[test]
+    fn calendar_units_say_why() {
+        let err = parse_duration("P1Y").unwrap_err();
+        assert!(err.contains("calendar units"), "{err}");
+    }
+}
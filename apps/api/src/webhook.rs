@@ -0,0 +1,439 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use sqlx::SqlitePool;
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+use tokio::sync::Notify;
+
+use crate::{config::WebhookConfig, db, token, urlcheck};
+
+pub const EVENT_HEADER: &str = "x-amigo-event";
+pub const DELIVERY_HEADER: &str = "x-amigo-delivery";
+pub const TIMESTAMP_HEADER: &str = "x-amigo-timestamp";
+pub const SIGNATURE_HEADER: &str = "x-amigo-signature";
+
+// 30s, 1m, 2m, ... capped at 6h; gives up after the tenth miss, about
+// 4h15m (30s·(2⁹−1)) after the first one.
+const MAX_ATTEMPTS: i64 = 10;
+const BASE_BACKOFF_SECS: i64 = 30;
+const MAX_BACKOFF_SECS: i64 = 6 * 3600;
+const BATCH: i64 = 32;
+const POLL: Duration = Duration::from_secs(5);
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct Webhooks {
+    // Server-wide hooks: the operator's own URLs, internal ones included.
+    client: reqwest::Client,
+    // Per-link hooks: anyone's URL, so only public addresses, checked when
+    // the connection is made (no proxy that would resolve on its own).
+    guarded: reqwest::Client,
+    allow_private: bool,
+    cfg: WebhookConfig,
+    wake: Notify,
+}
+
+impl Webhooks {
+    // allow_private = links.allow_private_urls: per-link hooks may reach
+    // the private network too.
+    pub fn new(cfg: WebhookConfig, allow_private: bool) -> anyhow::Result<Self> {
+        let builder = || {
+            reqwest::Client::builder()
+                .timeout(TIMEOUT)
+                .redirect(reqwest::redirect::Policy::none())
+                .user_agent("amigo-webhook/1")
+        };
+        let guarded = if allow_private {
+            builder().build()?
+        } else {
+            builder()
+                .dns_resolver(Arc::new(PublicOnly))
+                .no_proxy()
+                .build()?
+        };
+        Ok(Self {
+            client: builder().build()?,
+            guarded,
+            allow_private,
+            cfg,
+            wake: Notify::new(),
+        })
+    }
+
+    // Queue one event for the server-wide hooks plus the link's own hook
+    // (url, secret). Failing to queue is logged, never fails the request.
+    pub async fn enqueue(
+        &self,
+        pool: &SqlitePool,
+        link_hook: Option<(&str, &str)>,
+        event: &str,
+        payload: &serde_json::Value,
+        now: i64,
+    ) {
+        let body = payload.to_string();
+        let targets = self
+            .cfg
+            .urls
+            .iter()
+            .map(|url| (url.as_str(), None))
+            .chain(link_hook.map(|(url, secret)| (url, Some(secret))));
+
+        let mut queued = false;
+        for (url, secret) in targets {
+            match db::enqueue_delivery(pool, url, secret, event, &body, now).await {
+                Ok(()) => queued = true,
+                Err(err) => tracing::error!(error = ?err, event, "webhook enqueue failed"),
+            }
+        }
+        if queued {
+            self.wake.notify_one();
+        }
+    }
+
+    // Delivery worker: drains what's due, then naps until woken or polled.
+    pub async fn run(&self, pool: SqlitePool) {
+        loop {
+            self.deliver_due(&pool).await;
+            tokio::select! {
+                _ = self.wake.notified() => {}
+                _ = tokio::time::sleep(POLL) => {}
+            }
+        }
+    }
+
+    async fn deliver_due(&self, pool: &SqlitePool) {
+        loop {
+            let now = crate::now_unix();
+            let due = match db::due_deliveries(pool, now, BATCH).await {
+                Ok(due) => due,
+                Err(err) => {
+                    tracing::error!(error = ?err, "webhook queue read failed");
+                    return;
+                }
+            };
+            let batch_len = due.len() as i64;
+
+            for delivery in due {
+                let outcome = self.send(&delivery, now).await;
+                let res = match outcome {
+                    Ok(()) => db::drop_delivery(pool, delivery.id).await,
+                    Err(err) if delivery.attempts + 1 >= MAX_ATTEMPTS => {
+                        tracing::warn!(
+                            id = delivery.id,
+                            url = %delivery.url,
+                            error = %err,
+                            "webhook gave up"
+                        );
+                        db::drop_delivery(pool, delivery.id).await
+                    }
+                    Err(err) => {
+                        let next_at = now + backoff(delivery.attempts);
+                        db::retry_delivery(pool, delivery.id, next_at, &err).await
+                    }
+                };
+                if let Err(err) = res {
+                    tracing::error!(error = ?err, "webhook queue update failed");
+                    return;
+                }
+            }
+
+            if batch_len < BATCH {
+                return;
+            }
+        }
+    }
+
+    async fn send(&self, delivery: &db::Delivery, now: i64) -> Result<(), String> {
+        let secret = delivery
+            .secret
+            .as_deref()
+            .or(self.cfg.secret.as_deref())
+            .ok_or("no signing secret configured")?;
+        // Per-link hooks were checked at dispatch, but the name may point
+        // elsewhere by now: PublicOnly resolves it again for this very
+        // connection. IP literals never reach the resolver.
+        let client = if delivery.secret.is_some() {
+            if !self.allow_private {
+                let url = reqwest::Url::parse(&delivery.url).map_err(|err| err.to_string())?;
+                let literal = match url.host() {
+                    Some(url::Host::Ipv4(ip)) => Some(ip.into()),
+                    Some(url::Host::Ipv6(ip)) => Some(ip.into()),
+                    _ => None,
+                };
+                if literal.is_some_and(|ip| !urlcheck::is_public_ip(ip)) {
+                    return Err("private address".to_string());
+                }
+            }
+            &self.guarded
+        } else {
+            &self.client
+        };
+        let ts = now.to_string();
+        let res = client
+            .post(&delivery.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, &delivery.event)
+            .header(DELIVERY_HEADER, delivery.id.to_string())
+            .header(TIMESTAMP_HEADER, &ts)
+            .header(SIGNATURE_HEADER, sign(secret, &ts, &delivery.payload))
+            .body(delivery.payload.clone())
+            .send()
+            .await
+            .map_err(|err| err.to_string())?;
+        if res.status().is_success() {
+            Ok(())
+        } else {
+            Err(format!("status {}", res.status()))
+        }
+    }
+}
+
+// Resolver for per-link hooks: connects to the public addresses of a name
+// only, so a hook can't be pointed at loopback, the LAN or the cloud
+// metadata endpoint by its DNS.
+struct PublicOnly;
+
+impl reqwest::dns::Resolve for PublicOnly {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        Box::pin(async move {
+            let host = name.as_str();
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, 0))
+                .await?
+                .filter(|addr| urlcheck::is_public_ip(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{host} has no public address").into());
+            }
+            let addrs: reqwest::dns::Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+// "sha256=" + hex HMAC-SHA256 over "<timestamp>.<body>"; the timestamp is
+// in the signed string so a captured delivery can't be replayed later.
+pub fn sign(secret: &str, ts: &str, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac accepts any key length");
+    mac.update(ts.as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    format!("sha256={}", token::to_hex(&mac.finalize().into_bytes()))
+}
+
+fn backoff(attempts: i64) -> i64 {
+    let shift = attempts.clamp(0, 20) as u32;
+    BASE_BACKOFF_SECS
+        .saturating_mul(1 << shift)
+        .min(MAX_BACKOFF_SECS)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{extract::State, http::HeaderMap, http::StatusCode, routing::post, Router};
+    use std::sync::{
+        atomic::{AtomicU16, Ordering},
+        Mutex,
+    };
+
+    use super::*;
+    use crate::{config::DatabaseConfig, migrate};
+
+    // A local receiver: records what it got, answers with `status`.
+    #[derive(Default)]
+    struct Receiver {
+        status: AtomicU16,
+        got: Mutex<Vec<(HeaderMap, String)>>,
+    }
+
+    async fn receive(
+        State(rx): State<Arc<Receiver>>,
+        headers: HeaderMap,
+        body: String,
+    ) -> StatusCode {
+        rx.got.lock().unwrap().push((headers, body));
+        StatusCode::from_u16(rx.status.load(Ordering::SeqCst)).unwrap()
+    }
+
+    async fn receiver(status: u16) -> (Arc<Receiver>, String) {
+        let rx = Arc::new(Receiver::default());
+        rx.status.store(status, Ordering::SeqCst);
+        let app = Router::new()
+            .route("/hook", post(receive))
+            .with_state(rx.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (rx, format!("http://{addr}/hook"))
+    }
+
+    async fn pool(url: &str) -> SqlitePool {
+        let cfg = DatabaseConfig {
+            url: url.to_string(),
+            max_connections: 1,
+            ..DatabaseConfig::default()
+        };
+        let pool = db::connect(&cfg).await.unwrap();
+        migrate::run(&pool, false, 0).await.unwrap();
+        pool
+    }
+
+    // Server-wide hook at `url`, signed with "k3y".
+    fn hooks(url: &str) -> Webhooks {
+        let cfg = WebhookConfig {
+            urls: vec![url.to_string()],
+            secret: Some("k3y".to_string()),
+        };
+        Webhooks::new(cfg, false).unwrap()
+    }
+
+    async fn queued(pool: &SqlitePool) -> Vec<(i64, i64)> {
+        sqlx::query_as("SELECT attempts, next_attempt_at FROM webhook_deliveries")
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn sign_is_hmac_over_timestamp_dot_body() {
+        assert_eq!(
+            sign("k3y", "1700000000", r#"{"event":"link.opened"}"#),
+            "sha256=870c27e0d16289ca09c71b8f6ff144749f1dfcee846d61c1abf28d2853ffdfc0"
+        );
+        assert_ne!(
+            sign("k3y", "1700000001", r#"{"event":"link.opened"}"#),
+            sign("k3y", "1700000000", r#"{"event":"link.opened"}"#)
+        );
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        for (attempts, secs) in [
+            (-1, 30),
+            (0, 30),
+            (1, 60),
+            (2, 120),
+            (8, 7680),
+            (9, 15360),
+            (10, MAX_BACKOFF_SECS),
+            (64, MAX_BACKOFF_SECS),
+        ] {
+            assert_eq!(backoff(attempts), secs, "attempts {attempts}");
+        }
+    }
+
+    #[tokio::test]
+    async fn delivers_signed_and_drops_from_the_queue() {
+        let (rx, url) = receiver(200).await;
+        let pool = pool("sqlite::memory:").await;
+        let hooks = hooks(&url);
+        let payload = serde_json::json!({ "event": "link.opened", "slug": "abc123" });
+        hooks
+            .enqueue(&pool, None, "link.opened", &payload, crate::now_unix())
+            .await;
+        hooks.deliver_due(&pool).await;
+        assert!(queued(&pool).await.is_empty());
+
+        let got = rx.got.lock().unwrap();
+        assert_eq!(got.len(), 1);
+        let (headers, body) = &got[0];
+        let header = |name: &str| headers[name].to_str().unwrap().to_string();
+        assert_eq!(header(EVENT_HEADER), "link.opened");
+        assert_eq!(body, &payload.to_string());
+        assert_eq!(
+            header(SIGNATURE_HEADER),
+            sign("k3y", &header(TIMESTAMP_HEADER), body)
+        );
+    }
+
+    #[tokio::test]
+    async fn server_error_is_rescheduled_then_given_up() {
+        let (rx, url) = receiver(503).await;
+        let pool = pool("sqlite::memory:").await;
+        let hooks = hooks(&url);
+        let before = crate::now_unix();
+        hooks
+            .enqueue(&pool, None, "link.burned", &serde_json::json!({}), before)
+            .await;
+        hooks.deliver_due(&pool).await;
+
+        let rows = queued(&pool).await;
+        assert_eq!(rows.len(), 1);
+        let (attempts, next_at) = rows[0];
+        assert_eq!(attempts, 1);
+        assert!(next_at >= before + BASE_BACKOFF_SECS, "next at {next_at}");
+        // Not due yet: nothing goes out.
+        hooks.deliver_due(&pool).await;
+        assert_eq!(rx.got.lock().unwrap().len(), 1);
+
+        // The last miss drops it.
+        sqlx::query("UPDATE webhook_deliveries SET attempts = ?1, next_attempt_at = 0")
+            .bind(MAX_ATTEMPTS - 1)
+            .execute(&pool)
+            .await
+            .unwrap();
+        hooks.deliver_due(&pool).await;
+        assert_eq!(rx.got.lock().unwrap().len(), 2);
+        assert!(queued(&pool).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn queue_survives_a_restart() {
+        let (rx, url) = receiver(200).await;
+        let path = std::env::temp_dir().join(format!("amigo-hooks-{}.db", token::gen_token()));
+        let db_url = format!("sqlite:{}", path.display());
+
+        let pool1 = pool(&db_url).await;
+        hooks("http://127.0.0.1:9/unreachable")
+            .enqueue(
+                &pool1,
+                None,
+                "link.expired",
+                &serde_json::json!({}),
+                crate::now_unix(),
+            )
+            .await;
+        pool1.close().await;
+
+        let pool2 = pool(&db_url).await;
+        sqlx::query("UPDATE webhook_deliveries SET url = ?1")
+            .bind(&url)
+            .execute(&pool2)
+            .await
+            .unwrap();
+        hooks(&url).deliver_due(&pool2).await;
+        assert_eq!(rx.got.lock().unwrap().len(), 1);
+        assert!(queued(&pool2).await.is_empty());
+        pool2.close().await;
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn link_hooks_never_reach_private_addresses() {
+        let (rx, url) = receiver(200).await;
+        let pool = pool("sqlite::memory:").await;
+        let hooks = Webhooks::new(WebhookConfig::default(), false).unwrap();
+        let by_name = url.replace("127.0.0.1", "localhost");
+        for target in [url.as_str(), by_name.as_str()] {
+            hooks
+                .enqueue(
+                    &pool,
+                    Some((target, "s3cret")),
+                    "link.opened",
+                    &serde_json::json!({}),
+                    crate::now_unix(),
+                )
+                .await;
+        }
+        hooks.deliver_due(&pool).await;
+        assert!(rx.got.lock().unwrap().is_empty());
+        assert_eq!(queued(&pool).await.len(), 2);
+
+        // Unless the operator allows them.
+        let open = Webhooks::new(WebhookConfig::default(), true).unwrap();
+        sqlx::query("UPDATE webhook_deliveries SET next_attempt_at = 0")
+            .execute(&pool)
+            .await
+            .unwrap();
+        open.deliver_due(&pool).await;
+        assert_eq!(rx.got.lock().unwrap().len(), 2);
+    }
+}
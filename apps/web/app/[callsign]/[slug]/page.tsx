@@ -1,6 +1,7 @@
 import type { Metadata } from "next";
 import { headers } from "next/headers";
 import RoomClient from "../../r/[slug]/RoomClient";
+import { linkPath } from "../../lib/linkPath";
 
 type Props = {
   params: { callsign: string; slug: string };
@@ -62,11 +63,11 @@ function resolveSiteUrl(): string {
   return "http://localhost:3001";
 }
 
-async function fetchPeek(slug: string): Promise<PeekResponse | null> {
+async function fetchPeek(path: string): Promise<PeekResponse | null> {
   const base = (process.env.NEXT_PUBLIC_API_BASE || "http://localhost:3000").replace(/\/$/, "");
 
   try {
-    const res = await fetch(`${base}/api/peek/${slug}`, { cache: "no-store" });
+    const res = await fetch(`${base}/api/peek/${path}`, { cache: "no-store" });
     if (!res.ok) return null;
     return (await res.json()) as PeekResponse;
   } catch {
@@ -75,7 +76,7 @@ async function fetchPeek(slug: string): Promise<PeekResponse | null> {
 }
 
 export async function generateMetadata({ params, searchParams }: Props): Promise<Metadata> {
-  const peek = await fetchPeek(params.slug);
+  const peek = await fetchPeek(linkPath(params.slug, params.callsign));
   const emoji = peek?.emoji || DEFAULT_SIGNET;
   const gone = peek?.gone || peek?.exists === false;
   const hasUrl = peek?.has_url ?? false;
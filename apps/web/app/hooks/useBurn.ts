@@ -4,6 +4,9 @@ import { useCallback, useState } from "react";
 
 export type BurnStatus = "IDLE" | "BURNING" | "BURNED" | "ERROR";
 
+// Owner tokens are stored by the dispatching browser only (see Home).
+export const ownerTokenKey = (path: string) => `amigo:owner:${path}`;
+
 export function useBurn(slug: string, baseUrl: string) {
   const [status, setStatus] = useState<BurnStatus>("IDLE");
 
@@ -12,9 +15,11 @@ export function useBurn(slug: string, baseUrl: string) {
     setStatus("BURNING");
 
     try {
+      const token = window.localStorage.getItem(ownerTokenKey(slug));
       const res = await fetch(`${baseUrl}/api/burn/${slug}`, {
         method: "DELETE",
         keepalive: true,
+        headers: token ? { Authorization: `Bearer ${token}` } : undefined,
       });
       if (!res.ok) {
         setStatus("ERROR");
@@ -119,7 +119,11 @@ export default function JointClient({ id }: { id: string }) {
   };
 
   const burn = async () => {
-    await fetch(`${apiBase}/api/joint/${id}/burn`, { method: 'POST' });
+    const token = window.sessionStorage.getItem(`amigo:joint:${id}`);
+    await fetch(`${apiBase}/api/joint/${id}/burn`, {
+      method: 'POST',
+      headers: token ? { Authorization: `Bearer ${token}` } : undefined,
+    });
     setStatus('burned');
   };
 
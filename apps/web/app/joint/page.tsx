@@ -30,6 +30,9 @@ export default function JointLanding() {
       if (!res.ok) throw new Error('create_failed');
       const data = await res.json();
       if (data?.id) {
+        if (data.owner_token) {
+          window.sessionStorage.setItem(`amigo:joint:${data.id}`, data.owner_token);
+        }
         router.push(`/joint/${data.id}`);
         return;
       }
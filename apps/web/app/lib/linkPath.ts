@@ -0,0 +1,8 @@
+const DEFAULT_CALLSIGN = "@garden";
+
+// API path for a link: "slug" in the commons, "@crew/slug" in a callsign namespace.
+export function linkPath(slug: string, callsign?: string | null): string {
+  const cs = decodeURIComponent(callsign || "").toLowerCase();
+  if (!cs.startsWith("@") || cs === DEFAULT_CALLSIGN) return slug;
+  return `${cs}/${slug}`;
+}
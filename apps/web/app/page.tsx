@@ -4,8 +4,14 @@ import { useEffect, useMemo, useState } from "react";
 import type { CSSProperties, FormEvent } from "react";
 import { useTranslation } from "./i18n/useTranslation";
 import { LangSwitch } from "./i18n/LangSwitch";
+import { ownerTokenKey } from "./hooks/useBurn";
 
-type DispatchResponse = { short: string; original?: string | null; note?: string | null };
+type DispatchResponse = {
+  short: string;
+  original?: string | null;
+  note?: string | null;
+  owner_token?: string | null;
+};
 
 // TODO: Extend Musical Notes Code for 📻 signet (align with the 13-month energy calendar).
 const SIGNETS = ["💖", "👍", "📻", "🚬", "🐺", "🐸", "🌸", "🦅", "🐻", "🛰️", "⚓", "🫧"];
@@ -45,7 +51,9 @@ export default function Home() {
   const [note, setNote] = useState("");
   const [ttl, setTtl] = useState("7d");
   const [emoji, setEmoji] = useState("");
+  const [file, setFile] = useState<File | null>(null);
   const [hoveredSignet, setHoveredSignet] = useState<string | null>(null);
+  const [detectedSignet, setDetectedSignet] = useState<string | null>(null);
   const [replyTo, setReplyTo] = useState<string | null>(null);
   const [loading, setLoading] = useState(false);
   const [result, setResult] = useState<DispatchResponse | null>(null);
@@ -59,11 +67,11 @@ export default function Home() {
   const replyValue = replyTo?.trim() ?? "";
   const emojiValue = firstGrapheme(emoji.trim());
   const activeSignet = hoveredSignet || emojiValue;
-  const displaySignet = activeSignet || "💖";
+  const displaySignet = activeSignet || detectedSignet || "💖";
   const signetHintKey =
     (activeSignet && SIGNET_HINT_KEYS[activeSignet]) || "home.signet.hint";
   const isPetal = urlValue.length === 0;
-  const canSubmit = urlValue.length > 0 || noteValue.length > 0;
+  const canSubmit = urlValue.length > 0 || noteValue.length > 0 || file !== null;
 
   const payload = useMemo(
     () => ({
@@ -85,6 +93,27 @@ export default function Home() {
     }
   }, []);
 
+  // Preview what the server would pick for this url; a typed signet wins.
+  useEffect(() => {
+    if (!urlValue || emojiValue) {
+      setDetectedSignet(null);
+      return;
+    }
+    const ctrl = new AbortController();
+    const timer = window.setTimeout(() => {
+      fetch(`${base}/api/signets/detect?url=${encodeURIComponent(urlValue)}`, {
+        signal: ctrl.signal,
+      })
+        .then((res) => (res.ok ? res.json() : null))
+        .then((data: { signet?: string } | null) => setDetectedSignet(data?.signet ?? null))
+        .catch(() => undefined);
+    }, 300);
+    return () => {
+      window.clearTimeout(timer);
+      ctrl.abort();
+    };
+  }, [base, emojiValue, urlValue]);
+
   useEffect(() => {
     setCanShare(typeof navigator !== "undefined" && !!navigator.share);
   }, []);
@@ -98,14 +127,35 @@ export default function Home() {
     setCopied(false);
 
     try {
-      const res = await fetch(`${base}/api/dispatch`, {
-        method: "POST",
-        headers: { "Content-Type": "application/json" },
-        body: JSON.stringify(payload),
-      });
+      // A file goes as multipart: the same payload, plus the file part.
+      let init: RequestInit;
+      if (file) {
+        const form = new FormData();
+        form.append("payload", JSON.stringify(payload));
+        form.append("file", file);
+        init = { method: "POST", body: form };
+      } else {
+        init = {
+          method: "POST",
+          headers: { "Content-Type": "application/json" },
+          body: JSON.stringify(payload),
+        };
+      }
+      const res = await fetch(`${base}/api/dispatch`, init);
 
       if (!res.ok) throw new Error("dispatch failed");
       const data = (await res.json()) as DispatchResponse;
+      const path = decodeURIComponent(new URL(data.short).pathname.slice(1));
+      if (path && data.owner_token) {
+        window.localStorage.setItem(ownerTokenKey(path), data.owner_token);
+      }
+      if (replyValue) {
+        // Spend the view on the message we answered (Room defers it to here).
+        fetch(`${base}/api/commence/${replyValue}`, { method: "POST", keepalive: true }).catch(
+          () => undefined
+        );
+        setReplyTo(null);
+      }
       setResult(data);
       // Don't reset form - let user tweak and regenerate
     } catch (err) {
@@ -279,6 +329,18 @@ export default function Home() {
               />
             </div>
 
+            <div style={styles.fieldGroup}>
+              <label style={styles.label}>{t("home.file")}</label>
+              <input
+                type="file"
+                accept="image/*,application/pdf,audio/*"
+                onChange={(e) => setFile(e.target.files?.[0] ?? null)}
+                style={styles.input}
+                disabled={loading}
+              />
+              <p style={styles.hint}>{t("home.file.hint")}</p>
+            </div>
+
             <p style={styles.modeHint}>
               {isPetal ? t("home.hint.petal") : t("home.hint.link")}
             </p>
@@ -6,6 +6,7 @@ import { SignalLost } from '../../components/SignalLost';
 import { useBurn } from '../../hooks/useBurn';
 import { useTranslation } from '../../i18n/useTranslation';
 import { LangSwitch } from '../../i18n/LangSwitch';
+import { linkPath } from '../../lib/linkPath';
 
 const SCRAMBLE_CHARS = '█▓▒░<>--=+~'.split('');
 const SCRAMBLE_FRAMES = 16;
@@ -19,7 +20,12 @@ type Resolve = {
   expires_at?: number | null;
   reply_to?: string | null;
   emoji?: string | null;
+  attachment?: { content_type: string; filename?: string | null; size: number } | null;
 };
+type Commence = {
+  attachment?: { token: string; url: string; content_type: string; filename?: string | null };
+};
+type Attachment = { src: string; type: string; filename: string | null };
 
 function firstGrapheme(input?: string | null): string | null {
   if (!input) return null;
@@ -36,14 +42,18 @@ export default function RoomClient({ params }: Props) {
   const { t, lang } = useTranslation();
   const base = (process.env.NEXT_PUBLIC_API_BASE || 'http://localhost:3000').replace(/\/$/, '');
   const [data, setData] = useState<Resolve | null>(null);
-  const [status, setStatus] = useState<'loading' | 'ready' | 'gone' | 'burned'>('loading');
+  const [status, setStatus] = useState<'loading' | 'ready' | 'gone' | 'burned' | 'blocked' | 'attachment'>(
+    'loading'
+  );
+  const [attachment, setAttachment] = useState<Attachment | null>(null);
   const [auto, setAuto] = useState(false);
   const [mounted, setMounted] = useState(false);
   const [view, setView] = useState<'sealed' | 'tuning' | 'open'>('sealed');
   const [displayContent, setDisplayContent] = useState('');
   const frameRef = useRef(0);
   const charArrayRef = useRef<string[]>([]);
-  const { status: burnStatus, triggerBurn } = useBurn(params.slug, base);
+  const path = linkPath(params.slug, params.callsign);
+  const { status: burnStatus, triggerBurn } = useBurn(path, base);
 
   const ms = 3600; // Breathing cycle (mai somatic)
   const redirectTo = data?.url ?? null;
@@ -57,8 +67,9 @@ export default function RoomClient({ params }: Props) {
 
   useEffect(() => {
     let active = true;
-    fetch(`${base}/api/resolve/${params.slug}`)
+    fetch(`${base}/api/resolve/${path}`)
       .then(async (r) => {
+        if (r.status === 403) throw new Error('blocked');
         if (!r.ok) throw new Error('not-found');
         return (await r.json()) as Resolve;
       })
@@ -67,14 +78,14 @@ export default function RoomClient({ params }: Props) {
         setData(json);
         setStatus('ready');
       })
-      .catch(() => {
+      .catch((err: Error) => {
         if (!active) return;
-        setStatus('gone');
+        setStatus(err.message === 'blocked' ? 'blocked' : 'gone');
       });
     return () => {
       active = false;
     };
-  }, [base, params.slug]);
+  }, [base, path]);
 
   // Proof of Breath: Commit journey before redirect
   const commitAndGo = async () => {
@@ -82,7 +93,7 @@ export default function RoomClient({ params }: Props) {
 
     // Fire & forget: Ping backend to increment views (keepalive ensures it completes)
     try {
-      const res = await fetch(`${base}/api/commence/${params.slug}`, {
+      const res = await fetch(`${base}/api/commence/${path}`, {
         method: 'POST',
         keepalive: true, // Critical: Request survives page navigation
       });
@@ -90,6 +101,23 @@ export default function RoomClient({ params }: Props) {
         setStatus('gone');
         return;
       }
+      // The file comes only now, on the grant this commence handed out.
+      if (res.ok && data.attachment) {
+        const grant = ((await res.json().catch(() => null)) as Commence | null)?.attachment;
+        const file = grant
+          ? await fetch(grant.url, { headers: { 'x-attachment-token': grant.token } })
+          : null;
+        if (file?.ok && grant) {
+          const blob = await file.blob();
+          setAttachment({
+            src: URL.createObjectURL(blob),
+            type: grant.content_type,
+            filename: grant.filename ?? null,
+          });
+        }
+        setStatus('attachment');
+        return;
+      }
     } catch (e) {
       console.error('Ghost walk:', e);
       if (!data.url) {
@@ -130,25 +158,12 @@ export default function RoomClient({ params }: Props) {
     }
   };
 
-  const replyNow = async () => {
+  // The reply must reference a live link, so the view is spent by Home
+  // once the reply is dispatched (see handleSubmit there).
+  const replyNow = () => {
     if (!data) return;
-    try {
-      const res = await fetch(`${base}/api/commence/${params.slug}`, {
-        method: 'POST',
-        keepalive: true,
-      });
-      if (!res.ok) {
-        setStatus('gone');
-        return;
-      }
-    } catch (e) {
-      console.error('Reply burn failed:', e);
-      setStatus('gone');
-      return;
-    }
-
     const qs = new URLSearchParams();
-    qs.set('reply_to', params.slug);
+    qs.set('reply_to', path);
     if (lang) {
       qs.set('lang', lang);
     }
@@ -205,6 +220,27 @@ export default function RoomClient({ params }: Props) {
     return () => clearInterval(interval);
   }, [view, data, t, shouldAuto]);
 
+  if (status === 'blocked') {
+    return (
+      <main style={styles.main}>
+        <div style={styles.woodPlate}>
+          <div style={{ ...styles.card, position: 'relative' }}>
+            <p style={{ opacity: 0.85, marginBottom: 14, fontSize: 16, textAlign: 'center' }}>{t('room.blocked.title')}</p>
+            <p style={{ opacity: 0.65, lineHeight: 1.6, fontSize: 14, textAlign: 'center', color: 'var(--text-muted)' }}>{t('room.blocked.body')}</p>
+            <div style={{ marginTop: 20, textAlign: 'center' }}>
+              <a href="/" style={styles.shellLink}>
+                {t('room.gone.cta')}
+              </a>
+            </div>
+            <div style={styles.langWrap}>
+              <LangSwitch />
+            </div>
+          </div>
+        </div>
+      </main>
+    );
+  }
+
   if (status === 'gone') {
     return (
       <main style={styles.main}>
@@ -229,6 +265,49 @@ export default function RoomClient({ params }: Props) {
     );
   }
 
+  if (status === 'attachment') {
+    const leave = () => {
+      if (data?.url) {
+        window.location.href = data.url;
+      } else {
+        setStatus('burned');
+      }
+    };
+    return (
+      <main style={styles.main}>
+        <div style={styles.woodPlate}>
+          <div style={{ ...styles.card, position: 'relative' }}>
+            {data?.note ? <p style={styles.msgText}>“{data.note}”</p> : null}
+            {!attachment ? (
+              <p style={{ opacity: 0.65, fontSize: 14, textAlign: 'center', color: 'var(--text-muted)' }}>
+                {t('room.attachment.gone')}
+              </p>
+            ) : attachment.type.startsWith('image/') ? (
+              <img src={attachment.src} alt={attachment.filename ?? ''} style={{ maxWidth: '100%', borderRadius: 12 }} />
+            ) : attachment.type.startsWith('audio/') ? (
+              <audio src={attachment.src} controls style={{ width: '100%' }} />
+            ) : (
+              <p style={{ textAlign: 'center' }}>
+                <a href={attachment.src} download={attachment.filename ?? 'amigo.pdf'} style={styles.shellLink}>
+                  📜 {attachment.filename ?? 'PDF'}
+                </a>
+              </p>
+            )}
+            <div style={{ marginTop: 20, textAlign: 'center' }}>
+              <button
+                type="button"
+                onClick={leave}
+                style={{ ...styles.shellLink, background: 'none', border: 'none', cursor: 'pointer' }}
+              >
+                {t('room.attachment.cta')}
+              </button>
+            </div>
+          </div>
+        </div>
+      </main>
+    );
+  }
+
   if (burnStatus === 'BURNED' || status === 'burned') {
     return <SignalLost />;
   }